@@ -0,0 +1,250 @@
+// Headless checks for level files, meant for CI:
+//
+//   level_tool validate <level.ron>.. [--assets <dir>] [--root <name>=<dir>]..
+//   level_tool tree <level.ron>
+//   level_tool convert <input.ron> <output.ron|output.ronb>
+//   level_tool upgrade <level.ron>..
+//
+// Named asset roots are read from `asset_roots.ron` in the assets folder like in the editor,
+// `--root` adds or replaces one.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use bevy::utils::{HashMap, HashSet};
+
+use kns_bevy_editor3d::{Ron, RonLoader, RonNode, EditorId, ObjectType, AssetRoot, serialize_level, read_asset_roots, to_file_path};
+
+const USAGE: &str = "usage:
+    level_tool validate <level.ron>.. [--assets <dir>] [--root <name>=<dir>]..
+    level_tool tree <level.ron>
+    level_tool convert <input.ron> <output.ron|output.ronb>
+    level_tool upgrade <level.ron>..";
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut assets_path = PathBuf::from("assets");
+
+    if let Some(index) = args.iter().position(|arg| arg == "--assets") {
+        if index + 1 >= args.len() {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+
+        assets_path = PathBuf::from(args.remove(index + 1));
+        args.remove(index);
+    }
+
+    let mut roots = match read_asset_roots(&assets_path) {
+        Ok(roots) => roots,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    while let Some(index) = args.iter().position(|arg| arg == "--root") {
+        let root = match args.get(index + 1).and_then(|arg| arg.split_once('=')) {
+            Some((name, path)) => AssetRoot::new(name, path),
+            None => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        roots.retain(|existing| existing.name != root.name);
+        roots.push(root);
+        args.drain(index..index + 2);
+    }
+
+    let result = match args.first().map(|arg| arg.as_str()) {
+        Some("validate") if args.len() > 1 => validate(&args[1..], &assets_path, &roots),
+        Some("tree") if args.len() == 2 => tree(Path::new(&args[1])),
+        Some("convert") if args.len() == 3 => convert(Path::new(&args[1]), Path::new(&args[2])),
+        Some("upgrade") if args.len() > 1 => upgrade(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn load(path: &Path) -> Result<Ron, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    RonLoader::default()
+        .parse_bytes(&bytes)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn validate(paths: &[String], assets_path: &Path, roots: &[AssetRoot]) -> Result<(), String> {
+    let mut visited = HashSet::new();
+    let mut errors = 0;
+
+    for path in paths.iter() {
+        errors += validate_file(Path::new(path), assets_path, roots, &mut visited);
+    }
+
+    if errors == 0 {
+        println!("ok");
+        Ok(())
+    } else {
+        Err(format!("{} error(s) found", errors))
+    }
+}
+
+// Nested ron objects are validated once each.
+fn validate_file(path: &Path, assets_path: &Path, roots: &[AssetRoot], visited: &mut HashSet<PathBuf>) -> usize {
+    if !visited.insert(path.to_path_buf()) {
+        return 0;
+    }
+
+    let ron = match load(path) {
+        Ok(ron) => ron,
+        Err(e) => {
+            println!("error: {}", e);
+            return 1;
+        }
+    };
+
+    let mut errors = 0;
+
+    for issue in ron.validate() {
+        if issue.is_error() {
+            errors += 1;
+            println!("error: {}: {}", path.display(), issue);
+        } else {
+            println!("warning: {}: {}", path.display(), issue);
+        }
+    }
+
+    for (index, object) in ron.objects.iter().enumerate() {
+        let object_path = match &object.path {
+            Some(object_path) => object_path,
+            None => continue,
+        };
+
+        let asset_path = object_path.display().to_string();
+        let asset_path = asset_path.split('#').next().unwrap_or_default();
+
+        let file_path = match to_file_path(assets_path, roots, Path::new(asset_path)) {
+            Some(file_path) => file_path,
+            None => {
+                errors += 1;
+                println!("error: {}: object {} uses an unknown asset root in {}", path.display(), index, asset_path);
+                continue;
+            }
+        };
+
+        if !file_path.exists() {
+            errors += 1;
+            println!("error: {}: object {} refers to missing asset {}", path.display(), index, file_path.display());
+            continue;
+        }
+
+        if object.object_type == ObjectType::Ron {
+            errors += validate_file(&file_path, assets_path, roots, visited);
+        }
+    }
+
+    // Layer files are next to the master level.
+    for layer in ron.layers.iter() {
+        let layer_path = path.parent().unwrap_or(Path::new("")).join(&layer.path);
+
+        if !layer_path.exists() {
+            errors += 1;
+            println!("error: {}: layer {} refers to missing file {}", path.display(), layer.name, layer_path.display());
+            continue;
+        }
+
+        errors += validate_file(&layer_path, assets_path, roots, visited);
+    }
+
+    errors
+}
+
+fn tree(path: &Path) -> Result<(), String> {
+    let ron = load(path)?;
+
+    println!("{} (version {}, {} objects, {} nodes)", path.display(), ron.version, ron.objects.len(), ron.nodes.len());
+
+    let nodes: HashMap<EditorId, &RonNode> = ron.nodes.iter().map(|node| (node.id, node)).collect();
+
+    let mut visited = HashSet::new();
+
+    for node in ron.nodes.iter() {
+        if !node.has_parent {
+            print_node(&ron, node, &nodes, &mut visited, 1);
+        }
+    }
+
+    Ok(())
+}
+
+// A node that was printed already is named, not printed again, so cyclic levels end.
+fn print_node(ron: &Ron, node: &RonNode, nodes: &HashMap<EditorId, &RonNode>, visited: &mut HashSet<EditorId>, depth: usize) {
+    visited.insert(node.id);
+
+    let description = match ron.objects.get(node.object) {
+        Some(object) => match &object.path {
+            Some(path) => format!("{:?} {}", object.object_type, path.display()),
+            None => format!("{:?}", object.object_type),
+        },
+        None => format!("missing object {}", node.object),
+    };
+
+    println!(
+        "{}{} {} at {:?}",
+        "  ".repeat(depth),
+        node.id.0,
+        description,
+        node.transform.translation,
+    );
+
+    for component in node.components.iter() {
+        println!("{}  + {}", "  ".repeat(depth), component.type_name);
+    }
+
+    for ron_override in node.overrides.iter() {
+        println!("{}  ~ override of prefab node {}", "  ".repeat(depth), ron_override.node.0);
+    }
+
+    for child in node.childrens.iter() {
+        match nodes.get(child) {
+            Some(_) if visited.contains(child) => println!("{}  cyclic child {}", "  ".repeat(depth), child.0),
+            Some(child) => print_node(ron, child, nodes, visited, depth + 1),
+            None => println!("{}  missing child {}", "  ".repeat(depth), child.0),
+        }
+    }
+}
+
+fn convert(input: &Path, output: &Path) -> Result<(), String> {
+    let mut ron = load(input)?;
+
+    ron.version = kns_bevy_editor3d::RON_VERSION;
+    ron.sort();
+
+    let serialized = serialize_level(&ron, output).map_err(|e| e.to_string())?;
+
+    std::fs::write(output, serialized).map_err(|e| format!("{}: {}", output.display(), e))?;
+
+    println!("{} -> {} (version {})", input.display(), output.display(), ron.version);
+
+    Ok(())
+}
+
+// Rewrites levels in place with the current format, e.g. to drop old absolute asset paths.
+fn upgrade(paths: &[String]) -> Result<(), String> {
+    for path in paths.iter() {
+        convert(Path::new(path), Path::new(path))?;
+    }
+
+    Ok(())
+}
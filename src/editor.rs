@@ -79,11 +79,6 @@ fn setup(
             bevy_transform_gizmo::GizmoPickSource::default(),
             MyCamera,
         )); 
-
-    commands.insert_resource(AmbientLight {
-        color: Color::WHITE,
-        brightness: 0.5,
-    });
 }
 
 fn setup_input(mut commands: Commands) {
@@ -121,6 +116,9 @@ fn process_clear_level (
     mut commands: Commands,
     mut select_state: ResMut<SelectState>,
     mut file_state: ResMut<FileState>,
+    mut dependencies: ResMut<RonDependencies>,
+    mut layers: ResMut<LevelLayers>,
+    mut settings: ResMut<LevelSettings>,
     mut reader: EventReader<ClearLevelEvent>,
     obj_query: Query<Entity, (With<Object>, Without<Parent>)>,
 ) {
@@ -132,6 +130,10 @@ fn process_clear_level (
 
     file_state.current_file_path = None; 
 
+    dependencies.clear();
+    layers.clear();
+    *settings = LevelSettings::default();
+
     select_state.entity = None;
 
     log::info!("process_clear_level");
@@ -147,9 +149,12 @@ fn process_input (
     mut commands: Commands,
     mut select_state: ResMut<SelectState>,
     mut editor_state: ResMut<MyEditorState>,
+    layers: Res<LevelLayers>,
     camera_state: Res<CameraState>,
     pick_query: Query<&bevy_mod_raycast::RaycastSource<ObjectRaycastSet>>,
     transform_query: Query<&GlobalTransform>,
+    prefab_query: Query<&PrefabNode>,
+    id_map: Res<EditorIdMap>,
     mut reader: EventReader<InputObjectEvent>,
     mut add_writer: EventWriter<AddObjectEvent>,
 ) {
@@ -176,16 +181,30 @@ fn process_input (
 
                 let entity = commands.spawn_empty().id();
 
-                if select_state.set_child {
-                    if let Some(parent_entity) = select_state.entity {
-                        if let Ok(global_transform) = transform_query.get(parent_entity) {
-                            transform = Some( GlobalTransform::from(transform.unwrap()).reparented_to(global_transform));
-                        }
+                let mut is_root = true;
+
+                // Nodes of a prefab instance are saved in the prefab file, not in the level,
+                // so a child added to one would be lost. It goes to the instance root instead.
+                let parent_entity = select_state.entity.filter(|_| select_state.set_child).and_then(|selected| {
+                    match prefab_query.get(selected) {
+                        Ok(node) => {
+                            log::warn!("process_input: the object is added to the prefab instance, not to its node");
+                            id_map.get(&node.instance)
+                        },
+                        Err(_) => Some(selected),
+                    }
+                });
+
+                if let Some(parent_entity) = parent_entity {
+                    is_root = false;
+
+                    if let Ok(global_transform) = transform_query.get(parent_entity) {
+                        transform = Some( GlobalTransform::from(transform.unwrap()).reparented_to(global_transform));
+                    }
 
-                        if let Some(mut entity_commands) = commands.get_entity(parent_entity) {
-                            entity_commands.add_child(entity);
-                        } 
-                    }               
+                    if let Some(mut entity_commands) = commands.get_entity(parent_entity) {
+                        entity_commands.add_child(entity);
+                    } 
                 }
 
                 let collider = if select_state.generate_collider {
@@ -205,19 +224,23 @@ fn process_input (
                     None
                 };
 
-                let (object_type, path) = if_none_continue!(editor_state.selected_object.clone());
+                let mut object = if_none_continue!(editor_state.selected_object.clone());
 
-                let object = Object {
-                    object_type,
-                    path: Some(path),
-                    collider,    
-                };
+                if let Some(name) = layers.active.clone().filter(|_| is_root) {
+                    commands.entity(entity).insert(LevelLayer { name });
+                }
+
+                if matches!(object.object_type, ObjectType::Scene | ObjectType::Mesh | ObjectType::Node | ObjectType::Primitive) {
+                    object.collider = collider;
+                }
 
                 add_writer.send(AddObjectEvent {
                     entity: Some(entity),
+                    id: None,
                     object: Some(object),
                     transform,
                     selected: true,
+                    overrides: Vec::new(),
                 });
             },
 
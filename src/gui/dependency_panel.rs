@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::objects::{RonDependencies, format_chain};
+
+use super::FileState;
+
+
+#[derive(Default, Resource)]
+pub struct DependencyPanelState {
+    pub open: bool,
+    pub selected: Option<PathBuf>,
+}
+
+pub fn process_dependency_panel (
+    mut panel_state: ResMut<DependencyPanelState>,
+    file_state: Res<FileState>,
+    dependencies: Res<RonDependencies>,
+    mut contexts: EguiContexts,
+) {
+    if !panel_state.open {
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+
+    let mut open = panel_state.open;
+    let mut selected = panel_state.selected.clone();
+
+    egui::Window::new("Dependencies")
+        .open(&mut open)
+        .default_width(300.0)
+        .show(ctx, |ui| {
+            for chain in dependencies.cycles().iter() {
+                ui.colored_label(egui::Color32::RED, "cycle: ".to_string() + &format_chain(chain));
+            }
+
+            let level = file_state.current_file_path
+                .as_ref()
+                .map(|path| file_state.to_asset_path(path).unwrap_or(path.clone()));
+
+            ui.collapsing("Level depends on", |ui| {
+                let level = match level {
+                    Some(level) => level,
+                    None => {
+                        ui.label("-");
+                        return;
+                    }
+                };
+
+                for path in dependencies.dependencies(&level).into_iter() {
+                    let is_selected = selected.as_ref() == Some(&path);
+
+                    if ui.selectable_label(is_selected, path.display().to_string()).clicked() {
+                        selected = Some(path);
+                    }
+                }
+            });
+
+            ui.collapsing("Levels", |ui| {
+                for path in dependencies.files().into_iter() {
+                    let is_selected = selected.as_ref() == Some(path);
+
+                    if ui.selectable_label(is_selected, path.display().to_string()).clicked() {
+                        selected = Some(path.clone());
+                    }
+                }
+            });
+
+            ui.separator();
+
+            let path = match selected.as_ref() {
+                Some(path) => path,
+                None => {
+                    ui.label("Select a file to see the levels using it.");
+                    return;
+                }
+            };
+
+            ui.label(path.display().to_string() + " is used by:");
+
+            let users = dependencies.users(path);
+
+            if users.is_empty() {
+                ui.label("-");
+            }
+
+            for user in users.iter() {
+                ui.label(user.display().to_string());
+            }
+        });
+
+    panel_state.open = open;
+    panel_state.selected = selected;
+}
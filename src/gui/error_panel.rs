@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::objects::LoadErrors;
+
+
+#[derive(Default, Resource)]
+pub struct ErrorPanelState {
+    pub open: bool,
+    // Errors already shown, the window opens again on new ones.
+    seen: usize,
+}
+
+pub fn process_error_panel (
+    mut panel_state: ResMut<ErrorPanelState>,
+    mut load_errors: ResMut<LoadErrors>,
+    mut contexts: EguiContexts,
+) {
+    if load_errors.errors.len() > panel_state.seen {
+        panel_state.open = true;
+    }
+
+    panel_state.seen = load_errors.errors.len();
+
+    if !panel_state.open {
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+
+    let mut open = panel_state.open;
+
+    egui::Window::new("Load errors")
+        .open(&mut open)
+        .default_width(400.0)
+        .show(ctx, |ui| {
+            if load_errors.errors.is_empty() {
+                ui.label("No errors.");
+                return;
+            }
+
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("load_errors").striped(true).show(ui, |ui| {
+                    ui.strong("file");
+                    ui.strong("node");
+                    ui.strong("reason");
+                    ui.end_row();
+
+                    for error in load_errors.errors.iter() {
+                        ui.label(error.file.as_ref().map_or("-".to_string(), |file| file.display().to_string()));
+                        ui.label(error.node.map_or("-".to_string(), |node| node.0.to_string()));
+                        ui.colored_label(egui::Color32::RED, &error.reason);
+                        ui.end_row();
+                    }
+                });
+            });
+
+            if ui.button("Clear").clicked() {
+                load_errors.errors.clear();
+            }
+        });
+
+    panel_state.open = open;
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::objects::{LevelLayers, MoveToLayerEvent};
+
+
+// Layers of the current level, in the left panel.
+pub fn show_layers(
+    ui: &mut egui::Ui,
+    layers: &mut LevelLayers,
+    selected: Option<Entity>,
+    new_layer: &mut String,
+    move_writer: &mut EventWriter<MoveToLayerEvent>,
+) {
+    let mut active = layers.active.clone();
+    let mut removed = None;
+
+    egui::Grid::new("layers").striped(true).show(ui, |ui| {
+        ui.radio_value(&mut active, None, "level");
+        ui.label("");
+        ui.label("");
+
+        if ui.add_enabled(selected.is_some(), egui::Button::new("move selection")).clicked() {
+            if let Some(entity) = selected {
+                move_writer.send(MoveToLayerEvent { entity, layer: None });
+            }
+        }
+        ui.end_row();
+
+        for layer in layers.layers.iter_mut() {
+            ui.radio_value(&mut active, Some(layer.name.clone()), &layer.name);
+            ui.checkbox(&mut layer.visible, "visible");
+            ui.checkbox(&mut layer.locked, "locked");
+
+            if ui.add_enabled(selected.is_some(), egui::Button::new("move selection")).clicked() {
+                if let Some(entity) = selected {
+                    move_writer.send(MoveToLayerEvent { entity, layer: Some(layer.name.clone()) });
+                }
+            }
+
+            if ui.button("remove").clicked() {
+                removed = Some(layer.name.clone());
+            }
+            ui.end_row();
+        }
+    });
+
+    layers.active = active;
+
+    if let Some(name) = removed {
+        if rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Warning)
+            .set_title("Remove layer")
+            .set_description(&format!("Do you want to remove the layer {}? Its objects move to the level.", name))
+            .set_buttons(rfd::MessageButtons::YesNo)
+            .show()
+        {
+            layers.remove(&name);
+        }
+    }
+
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(new_layer);
+
+        if ui.button("Add layer").clicked() && layers.add(new_layer.trim()) {
+            new_layer.clear();
+        }
+    });
+}
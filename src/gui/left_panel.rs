@@ -1,18 +1,31 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use rfd::{MessageButtons, MessageLevel};
+use std::path::{Path, PathBuf};
 
 use super::{FileState, MyEditorState, SelectState};
+use super::error_panel::ErrorPanelState;
+use super::missing_panel::MissingPanelState;
+use super::layer_panel::show_layers;
 use crate::editor::ClearLevelEvent;
-use crate::objects::{LoadObjectEvent, ObjectType, ColliderType, Object};
+use crate::objects::{LoadObjectEvent, ImportObjectEvent, ObjectImporters, LightData, LightType, Primitive, PrimitiveShape, ParticleData, ObjectType, ColliderType, Object, LoadErrors, MissingAsset, LevelLayers, MoveToLayerEvent, BINARY_EXTENSION};
 
 pub fn process_left_panel(
     mut editor_state: ResMut<MyEditorState>,
     mut file_state: ResMut<FileState>,
     mut select_state: ResMut<SelectState>,
+    mut error_panel: ResMut<ErrorPanelState>,
+    load_errors: Res<LoadErrors>,
+    mut missing_panel: ResMut<MissingPanelState>,
+    missing_query: Query<(), With<MissingAsset>>,
+    mut layers: ResMut<LevelLayers>,
+    mut new_layer: Local<String>,
     mut contexts: EguiContexts,
     mut load_object_writer: EventWriter<LoadObjectEvent>,
+    importers: Res<ObjectImporters>,
+    mut import_writer: EventWriter<ImportObjectEvent>,
     mut clear_writer: EventWriter<ClearLevelEvent>,
+    mut move_writer: EventWriter<MoveToLayerEvent>,
 ) {
     let ctx = contexts.ctx_mut();
 
@@ -29,6 +42,24 @@ pub fn process_left_panel(
                 );
                 //}
 
+                if !load_errors.errors.is_empty() {
+                    let label = "load errors: ".to_string() + &load_errors.errors.len().to_string();
+
+                    if ui.button(egui::RichText::new(label).color(egui::Color32::RED)).clicked() {
+                        error_panel.open = !error_panel.open;
+                    }
+                }
+
+                let missing = missing_query.iter().count();
+
+                if missing != 0 {
+                    let label = "missing assets: ".to_string() + &missing.to_string();
+
+                    if ui.button(egui::RichText::new(label).color(egui::Color32::RED)).clicked() {
+                        missing_panel.open = !missing_panel.open;
+                    }
+                }
+
                 if ui.button("Clear all").clicked() {
                     if rfd::MessageDialog::new()
                         .set_level(MessageLevel::Warning)
@@ -74,69 +105,90 @@ pub fn process_left_panel(
                     info!("{:?}", assets_path.as_path());
                     if let Some(path) = rfd::FileDialog::new()
                         .set_directory(assets_path.as_path())
-                        .add_filter("ron", &["ron"])
+                        .add_filter("ron", &["ron", BINARY_EXTENSION])
                         .pick_file()
+                        .and_then(|path| asset_path_or_warn(&file_state, &path))
                     {
-                        let rel_path = path
-                            .display()
-                            .to_string()
-                            .replace(&file_state.assets_path.display().to_string(), "");
+                        let rel_path = path.display().to_string();
                         
                         editor_state
                             .objects
-                            .insert(rel_path, (ObjectType::Ron, path.clone()) );
+                            .insert(rel_path, Object {
+                                object_type: ObjectType::Ron,
+                                path: Some(path.clone()),
+                                ..Default::default()
+                            });
                         load_object_writer.send(LoadObjectEvent {path: path.clone()});
                     }
                 }
 
-                if ui.button("Load gltf scene").clicked() {
+                // The importer of the file adds its objects, gltf scenes, meshes and nodes
+                // are added when the file is loaded.
+                if ui.button("Import objects").clicked() {
                     //              current_path.push("map");
 
                     info!("{:?}", assets_path.as_path());
-                    if let Some(path) = rfd::FileDialog::new()
-                        .set_directory(assets_path.as_path())
-                        .add_filter("gltf", &["gltf"])
-                        .pick_file()
-                    {
-                        //                    state.selected_object = Some(ObjectType::Scene(path));
-                        let rel_path = path
-                            .display()
-                            .to_string()
-                            .replace(&file_state.assets_path.display().to_string(), "");
 
-                        editor_state
-                            .objects
-                            .insert("gltf_scene:".to_string() + &rel_path, (ObjectType::Scene, path.clone()) );
+                    let mut dialog = rfd::FileDialog::new()
+                        .set_directory(assets_path.as_path())
+                        .add_filter("all objects", &importers.extensions());
 
-                        load_object_writer.send(LoadObjectEvent {path: path.clone()});
+                    for importer in importers.importers.iter() {
+                        let extensions: Vec<&str> = importer.extensions.iter().map(String::as_str).collect();
+                        dialog = dialog.add_filter(&importer.name, &extensions);
                     }
-                }
-
-                if ui.button("Load gltf mesh").clicked() {
-                    //               current_path.push("objects");
 
-                    info!("{:?}", assets_path.as_path());
-
-                    if let Some(path) = rfd::FileDialog::new()
-                        .set_directory(assets_path.as_path())
-                        .add_filter("gltf", &["gltf"])
+                    if let Some(path) = dialog
                         .pick_file()
+                        .and_then(|path| asset_path_or_warn(&file_state, &path))
                     {
-                        let rel_path = path
-                            .display()
-                            .to_string()
-                            .replace(&file_state.assets_path.display().to_string(), "");
+                        match importers.find(&path) {
+                            Some(importer) => import_writer.send(ImportObjectEvent { importer: importer.name.clone(), path }),
+                            None => warn!("process_left_panel: no importer for {}", path.display()),
+                        }
+                    }
+                }
 
-                        editor_state
-                            .objects
-                            .insert("gltf_mesh:".to_string() + &rel_path,(ObjectType::Mesh, path.clone()) );
+                ui.horizontal(|ui| {
+                    for light_type in [LightType::Point, LightType::Spot, LightType::Directional] {
+                        if ui.button(light_type.to_string()).clicked() {
+                            editor_state.selected_object = Some(Object {
+                                object_type: ObjectType::Light,
+                                light: Some(LightData::new(light_type)),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                });
 
-                        load_object_writer.send(LoadObjectEvent {path: path.clone()});
+                ui.horizontal_wrapped(|ui| {
+                    for shape in PrimitiveShape::all() {
+                        if ui.button(shape.to_string()).clicked() {
+                            editor_state.selected_object = Some(Object {
+                                object_type: ObjectType::Primitive,
+                                primitive: Some(Primitive::new(shape)),
+                                ..Default::default()
+                            });
+                        }
                     }
+                });
+
+                if ui.button("particle emitter").clicked() {
+                    editor_state.selected_object = Some(Object {
+                        object_type: ObjectType::Particles,
+                        particles: Some(ParticleData::default()),
+                        ..Default::default()
+                    });
                 }
             });
 
 
+            ui.separator();
+
+            ui.collapsing("Layers", |ui| {
+                show_layers(ui, &mut layers, select_state.entity, &mut new_layer, &mut move_writer);
+            });
+
             ui.separator();
             ui.heading("Objects:");
             
@@ -228,3 +280,22 @@ pub fn process_left_panel(
             });
  //       }); 
 }
+
+// Objects are stored with asset paths, so files outside the asset roots can't be used.
+pub(super) fn asset_path_or_warn(file_state: &FileState, path: &Path) -> Option<PathBuf> {
+    let asset_path = file_state.to_asset_path(path);
+
+    if asset_path.is_none() {
+        rfd::MessageDialog::new()
+            .set_level(MessageLevel::Warning)
+            .set_title("Load object")
+            .set_description(&format!(
+                "{} is outside the assets folder and the asset roots!",
+                path.display()
+            ))
+            .set_buttons(MessageButtons::Ok)
+            .show();
+    }
+
+    asset_path
+}
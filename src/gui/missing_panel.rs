@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::objects::{MissingAsset, Object, ObjectImporters, RelinkAssetEvent};
+
+use super::FileState;
+use super::left_panel::asset_path_or_warn;
+
+
+#[derive(Default, Resource)]
+pub struct MissingPanelState {
+    pub open: bool,
+    // The window opens again when more objects are missing.
+    seen: usize,
+}
+
+pub fn process_missing_panel (
+    mut panel_state: ResMut<MissingPanelState>,
+    file_state: Res<FileState>,
+    importers: Res<ObjectImporters>,
+    missing_query: Query<&Object, With<MissingAsset>>,
+    mut contexts: EguiContexts,
+    mut relink_writer: EventWriter<RelinkAssetEvent>,
+) {
+    let mut missing: BTreeMap<PathBuf, usize> = BTreeMap::new();
+
+    for object in missing_query.iter() {
+        if let Some(path) = object.path.clone() {
+            *missing.entry(path).or_default() += 1;
+        }
+    }
+
+    let count = missing.values().sum();
+
+    if count > panel_state.seen {
+        panel_state.open = true;
+    }
+
+    panel_state.seen = count;
+
+    if !panel_state.open || missing.is_empty() {
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+
+    let mut open = panel_state.open;
+
+    egui::Window::new("Missing assets")
+        .open(&mut open)
+        .default_width(400.0)
+        .show(ctx, |ui| {
+            egui::Grid::new("missing_assets").striped(true).show(ui, |ui| {
+                for (path, count) in missing.iter() {
+                    ui.colored_label(egui::Color32::RED, path.display().to_string());
+                    ui.label(count.to_string() + " objects");
+
+                    if ui.button("Relink..").clicked() {
+                        // A .gltf file can be relinked to a .glb one.
+                        let extensions: Vec<String> = match importers.find(path) {
+                            Some(importer) => importer.extensions.clone(),
+                            None => path
+                                .extension()
+                                .map(|extension| extension.to_string_lossy().to_string())
+                                .into_iter()
+                                .collect(),
+                        };
+                        let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+
+                        let directory = file_state
+                            .to_file_path(path)
+                            .and_then(|file_path| file_path.parent().map(|parent| parent.to_path_buf()))
+                            .filter(|directory| directory.exists())
+                            .unwrap_or(file_state.assets_path.clone());
+
+                        if let Some(to) = rfd::FileDialog::new()
+                            .set_directory(directory)
+                            .add_filter(&extensions.join(", "), &extensions)
+                            .pick_file()
+                            .and_then(|to| asset_path_or_warn(&file_state, &to))
+                        {
+                            relink_writer.send(RelinkAssetEvent { from: path.clone(), to });
+                        }
+                    }
+
+                    ui.end_row();
+                }
+            });
+        });
+
+    panel_state.open = open;
+}
@@ -4,18 +4,28 @@ use rfd::*;
 use std::fmt::Debug;
 
 use crate::editor::ClearLevelEvent;
-use crate::objects::{LoadRonEvent, SaveRonEvent};
+use crate::objects::{LoadRonEvent, SaveRonEvent, ExportGltfEvent, ImportSceneEvent, ExportSceneEvent, AutosaveSettings, process_autosave, BINARY_EXTENSION};
 use crate::input::*;
 
+use self::dependency_panel::{DependencyPanelState, process_dependency_panel};
+use self::error_panel::{ErrorPanelState, process_error_panel};
+use self::missing_panel::{MissingPanelState, process_missing_panel};
+use self::settings_panel::{SettingsPanelState, process_settings_panel};
 use self::left_panel::process_left_panel;
 pub use self::my_state::*;
 use self::right_panel::process_right_panel;
 
 
+mod dependency_panel;
+mod error_panel;
+mod layer_panel;
+mod missing_panel;
 mod my_state;
+mod object_panel;
 mod left_panel;
 mod right_panel;
 mod select_panel;
+mod settings_panel;
 
 #[derive(PartialEq, Eq)]
 enum UiScaleType {
@@ -57,6 +67,10 @@ impl Plugin for WindowPlugin {
             .init_resource::<FileState>()
             .init_resource::<SelectState>()
             .init_resource::<RenderState>()
+            .init_resource::<DependencyPanelState>()
+            .init_resource::<ErrorPanelState>()
+            .init_resource::<MissingPanelState>()
+            .init_resource::<SettingsPanelState>()
             .add_event::<UiScaleEvent>()
             .add_event::<UiPanelEvent>()
             .add_plugin(InputPlugin::<Actions>::default())
@@ -65,9 +79,14 @@ impl Plugin for WindowPlugin {
             .add_systems((
                 process_input_events,
                 update_ui_scale_factor_system.after(process_input_events),
-                process_up_panel,
-                process_left_panel.after(process_up_panel),
+                // Autosave snapshots the level in the frame "Clear all" is clicked.
+                process_up_panel.before(process_autosave),
+                process_left_panel.after(process_up_panel).before(process_autosave),
                 process_right_panel.after(process_up_panel),
+                process_dependency_panel.after(process_up_panel),
+                process_settings_panel.after(process_up_panel),
+                process_error_panel.after(process_left_panel),
+                process_missing_panel.after(process_left_panel),
             ));
     }
 }
@@ -190,12 +209,19 @@ fn process_up_panel(
     mut editor_state: ResMut<MyEditorState>,
     mut render_state: ResMut<RenderState>,
     file_state: Res<FileState>,
+    select_state: Res<SelectState>,
+    mut autosave: ResMut<AutosaveSettings>,
+    mut dependency_panel: ResMut<DependencyPanelState>,
+    mut settings_panel: ResMut<SettingsPanelState>,
     mut contexts: EguiContexts,
 //    mut is_quit_open: Local<Option<bool>>,
     mut panel_writer: EventWriter<UiPanelEvent>,
     mut load_writer: EventWriter<LoadRonEvent>,
     mut save_writer: EventWriter<SaveRonEvent>,
     mut clear_writer: EventWriter<ClearLevelEvent>,
+    mut export_writer: EventWriter<ExportGltfEvent>,
+    mut import_scene_writer: EventWriter<ImportSceneEvent>,
+    mut export_scene_writer: EventWriter<ExportSceneEvent>,
 ) {
     let ctx = contexts.ctx_mut();
 
@@ -244,6 +270,7 @@ fn process_up_panel(
                                 save_writer.send(SaveRonEvent {
                                     path: file_state.current_file_path.clone(),
                                     root: None,
+                                    snapshot: false,
                                 });
                             }
                         }
@@ -257,9 +284,10 @@ fn process_up_panel(
                         if let Some(path) = rfd::FileDialog::new()
                             .set_directory(assets_path.as_path())
                             .add_filter(".ron", &["ron"])
+                            .add_filter(".ronb (binary)", &[BINARY_EXTENSION])
                             .save_file()
                         {
-                            save_writer.send(SaveRonEvent { path: Some(path), root: None });
+                            save_writer.send(SaveRonEvent { path: Some(path), root: None, snapshot: false });
                         }
                     }
     
@@ -270,7 +298,7 @@ fn process_up_panel(
 
                         if let Some(path) = rfd::FileDialog::new()
                             .set_directory(assets_path.as_path())
-                            .add_filter(".ron", &["ron"])
+                            .add_filter("level", &["ron", BINARY_EXTENSION])
                             .pick_file() {
                             clear_writer.send(ClearLevelEvent);
                             load_writer.send( LoadRonEvent { path: Some(path) });
@@ -278,6 +306,58 @@ fn process_up_panel(
                     }
 
 
+                    if ui.button("Export glTF..").clicked() {
+                        if file_state.qnt_loading_ogjects != 0 {
+                            rfd::MessageDialog::new()
+                                .set_level(MessageLevel::Warning)
+                                .set_title("Export glTF")
+                                .set_description("Objects are still loading!\nTry a few seconds later.")
+                                .set_buttons(MessageButtons::Ok)
+                                .show();
+                        } else if let Some(path) = rfd::FileDialog::new()
+                            .set_directory(assets_path.as_path())
+                            .add_filter(".glb", &["glb"])
+                            .add_filter(".gltf", &["gltf"])
+                            .save_file()
+                        {
+                            export_writer.send(ExportGltfEvent { path });
+                        }
+                    }
+
+                    if ui.button("Import scene (.scn.ron)..").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .set_directory(assets_path.as_path())
+                            .add_filter(".scn.ron", &["ron"])
+                            .pick_file()
+                        {
+                            import_scene_writer.send(ImportSceneEvent { path });
+                        }
+                    }
+
+                    if ui.button("Export scene (.scn.ron)..").clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .set_directory(assets_path.as_path())
+                            .add_filter(".scn.ron", &["ron"])
+                            .save_file()
+                        {
+                            export_scene_writer.send(ExportSceneEvent { path, root: None });
+                        }
+                    }
+
+                    if let Some(entity) = select_state.entity {
+                        if ui.button("Export selected as scene (.scn.ron)..").clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .set_directory(assets_path.as_path())
+                                .add_filter(".scn.ron", &["ron"])
+                                .save_file()
+                            {
+                                export_scene_writer.send(ExportSceneEvent { path, root: Some(entity) });
+                            }
+                        }
+                    }
+
+                    ui.checkbox(&mut autosave.enabled, "Autosave");
+
                     if ui.button("Quit").clicked() {
                         if rfd::MessageDialog::new()
                         .set_level(MessageLevel::Warning)
@@ -317,6 +397,14 @@ fn process_up_panel(
                 panel_writer.send( UiPanelEvent { action: UiEventType::Gltf } );
             }
 
+            if ui.button("Dependencies").clicked() {
+                dependency_panel.open = !dependency_panel.open;
+            }
+
+            if ui.button("Level settings").clicked() {
+                settings_panel.open = !settings_panel.open;
+            }
+
             egui::menu::menu_button(ui, "Render", |ui| {
                 ui.vertical(|ui| {
                     ui.checkbox(&mut render_state.hierarchy_enabled, "hierarchy");
@@ -1,13 +1,14 @@
-use bevy::{prelude::*, utils::HashMap};
-use std::path::PathBuf;
+use bevy::{asset::FileAssetIo, log, prelude::*, utils::HashMap};
+use std::path::{Path, PathBuf};
 
 use crate::objects::*;
 
 
 #[derive(Resource, Component)]
 pub struct MyEditorState {
-    pub selected_object: Option<(ObjectType, PathBuf)>,
-    pub objects: HashMap<String, (ObjectType, PathBuf)>,
+    // The palette entries are copied to the added objects.
+    pub selected_object: Option<Object>,
+    pub objects: HashMap<String, Object>,
 }
 
 impl Default for MyEditorState {
@@ -21,18 +22,21 @@ impl Default for MyEditorState {
 
 impl MyEditorState {
     pub fn get_selected_object_name(&self) -> String {
-        if let Some((selected_object, path)) = self.selected_object.clone() {
-            return match selected_object {
-                ObjectType::Scene => {
-                    get_name(&Some(path))
-                }
-                ObjectType::Mesh => {
-                    get_name(&Some(path))
+        if let Some(object) = self.selected_object.clone() {
+            return match object.object_type {
+                ObjectType::Scene | ObjectType::Mesh | ObjectType::Node => {
+                    get_name(&object.path) + &object.label.map_or(String::new(), |label| "#".to_string() + &label)
                 }
                 ObjectType::Ron => {
-                    get_name(&Some(path))
+                    get_name(&object.path)
                 }
 
+                ObjectType::Light => object.light.map_or("light".to_string(), |light| light.light_type.to_string()),
+
+                ObjectType::Primitive => object.primitive.map_or("primitive".to_string(), |primitive| primitive.shape.to_string()),
+
+                ObjectType::Particles => "particles".to_string(),
+
                 ObjectType::Collider => "collider".to_string(),
 
                 ObjectType::Empty => "empty".to_string(),
@@ -54,20 +58,26 @@ pub struct SelectState {
 pub struct FileState {
     pub qnt_loading_ogjects: usize,
     pub assets_path: PathBuf,    
+    pub asset_roots: Vec<AssetRoot>,
     pub current_file_path: Option<PathBuf>,
     pub load_handle: Option<Handle<Ron>>,
 }
 
 impl Default for FileState {
     fn default() -> Self {
-        let mut assets_path = std::env::current_exe().expect("Failed to get assets path");
-        assets_path.pop();
-        assets_path.push("assets");
+        // The folder `AssetServer` reads from with the default `AssetPlugin`.
+        let assets_path = FileAssetIo::get_base_path().join("assets");
+
+        let asset_roots = read_asset_roots(&assets_path).unwrap_or_else(|e| {
+            log::error!("FileState: failed to read the asset roots, {}", e);
+            Vec::new()
+        });
 
         Self {
             qnt_loading_ogjects: 0,
             current_file_path: None,
             assets_path,
+            asset_roots,
             load_handle: None,
         }
     }
@@ -77,6 +87,18 @@ impl FileState {
     pub fn get_file_name(&self) -> String {
         get_name(&self.current_file_path)
     }
+
+    pub fn to_asset_path(&self, path: &Path) -> Option<PathBuf> {
+        to_asset_path(&self.assets_path, &self.asset_roots, path)
+    }
+
+    pub fn to_load_path(&self, path: &Path) -> String {
+        to_load_path(&self.asset_roots, path)
+    }
+
+    pub fn to_file_path(&self, path: &Path) -> Option<PathBuf> {
+        to_file_path(&self.assets_path, &self.asset_roots, path)
+    }
 }
 
 fn get_name(path: &Option<PathBuf>) -> String {
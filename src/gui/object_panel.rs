@@ -0,0 +1,447 @@
+use std::path::PathBuf;
+use bevy::prelude::*;
+use bevy_egui::egui;
+
+use crate::objects::{Collider, ColliderData, ColliderType, LightData, LightType, Object, ObjectType, PrimitiveShape, MaterialOverride, MaterialAlphaMode, ParticleData, ParticleColorKey, ParticleSizeKey, TO_FLOAT, FROM_FLOAT};
+
+use super::FileState;
+use super::left_panel::asset_path_or_warn;
+
+const TEXTURE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "ktx2", "hdr", "tga"];
+
+
+// Properties of the selected object in the right panel, returns the object if it was edited.
+// Values are written back only when a widget changes, so the object isn't changed every frame.
+pub fn show_object_properties(ui: &mut egui::Ui, object: &Object, file_state: &FileState) -> Option<Object> {
+    let mut edited = object.clone();
+
+    if let Some(light) = edited.light.as_mut() {
+        ui.separator();
+        show_light(ui, light);
+    }
+
+    if edited.primitive.is_some() {
+        ui.separator();
+        show_primitive(ui, &mut edited);
+    }
+
+    if let Some(particles) = edited.particles.as_mut() {
+        ui.separator();
+        show_particles(ui, particles);
+    }
+
+    if matches!(edited.object_type, ObjectType::Scene | ObjectType::Mesh | ObjectType::Node | ObjectType::Primitive) {
+        ui.separator();
+        show_material(ui, &mut edited, file_state);
+    }
+
+    if edited != *object {
+        Some(edited)
+    } else {
+        None
+    }
+}
+
+fn show_light(ui: &mut egui::Ui, light: &mut LightData) {
+    ui.label("Light");
+
+    egui::Grid::new("light").num_columns(2).show(ui, |ui| {
+        let mut light_type = light.light_type;
+
+        ui.label("type");
+        egui::ComboBox::from_id_source("light_type")
+            .selected_text(light_type.to_string())
+            .show_ui(ui, |ui| {
+                for value in [LightType::Point, LightType::Spot, LightType::Directional] {
+                    ui.selectable_value(&mut light_type, value, value.to_string());
+                }
+            });
+        ui.end_row();
+
+        // Intensities of directional lights have another unit, so they start from the defaults.
+        if light_type != light.light_type {
+            *light = LightData {
+                color: light.color,
+                shadows_enabled: light.shadows_enabled,
+                ..LightData::new(light_type)
+            };
+        }
+
+        let mut color = light.get_color().as_rgba_f32();
+
+        ui.label("color");
+        if ui.color_edit_button_rgba_unmultiplied(&mut color).changed() {
+            light.set_color(Color::rgba(color[0], color[1], color[2], color[3]));
+        }
+        ui.end_row();
+
+        let mut intensity = light.get_intensity();
+
+        ui.label(if light.light_type == LightType::Directional { "illuminance" } else { "intensity" });
+        if ui.add(egui::DragValue::new(&mut intensity).speed(10.0).clamp_range(0.0..=400000.0)).changed() {
+            light.set_intensity(intensity);
+        }
+        ui.end_row();
+
+        if light.light_type != LightType::Directional {
+            let mut range = light.get_range();
+
+            ui.label("range");
+            if ui.add(egui::DragValue::new(&mut range).speed(0.1).clamp_range(0.0..=10000.0)).changed() {
+                light.set_range(range);
+            }
+            ui.end_row();
+
+            let mut radius = light.get_radius();
+
+            ui.label("radius");
+            if ui.add(egui::DragValue::new(&mut radius).speed(0.01).clamp_range(0.0..=100.0)).changed() {
+                light.set_radius(radius);
+            }
+            ui.end_row();
+        }
+
+        if light.light_type == LightType::Spot {
+            let mut inner_angle = light.get_inner_angle().to_degrees();
+            let mut outer_angle = light.get_outer_angle().to_degrees();
+
+            ui.label("inner angle");
+            if ui.add(egui::DragValue::new(&mut inner_angle).speed(0.5).clamp_range(0.0..=90.0)).changed() {
+                light.set_inner_angle(inner_angle.to_radians());
+            }
+            ui.end_row();
+
+            ui.label("outer angle");
+            if ui.add(egui::DragValue::new(&mut outer_angle).speed(0.5).clamp_range(0.0..=90.0)).changed() {
+                light.set_outer_angle(outer_angle.to_radians());
+            }
+            ui.end_row();
+        }
+
+        ui.label("shadows");
+        ui.checkbox(&mut light.shadows_enabled, "");
+        ui.end_row();
+
+        if light.shadows_enabled {
+            let mut depth_bias = light.get_shadow_depth_bias();
+            let mut normal_bias = light.get_shadow_normal_bias();
+
+            ui.label("depth bias");
+            if ui.add(egui::DragValue::new(&mut depth_bias).speed(0.001).clamp_range(0.0..=10.0)).changed() {
+                light.set_shadow_depth_bias(depth_bias);
+            }
+            ui.end_row();
+
+            ui.label("normal bias");
+            if ui.add(egui::DragValue::new(&mut normal_bias).speed(0.01).clamp_range(0.0..=10.0)).changed() {
+                light.set_shadow_normal_bias(normal_bias);
+            }
+            ui.end_row();
+        }
+    });
+}
+
+fn show_primitive(ui: &mut egui::Ui, object: &mut Object) {
+    ui.label("Primitive");
+
+    let mut has_collider = object.collider.is_some();
+    let primitive = crate::if_none_return!(object.primitive.as_mut());
+
+    egui::Grid::new("primitive").num_columns(2).show(ui, |ui| {
+        let mut shape = primitive.shape.to_string();
+
+        ui.label("shape");
+        egui::ComboBox::from_id_source("primitive_shape")
+            .selected_text(shape.clone())
+            .show_ui(ui, |ui| {
+                for value in PrimitiveShape::all() {
+                    ui.selectable_value(&mut shape, value.to_string(), value.to_string());
+                }
+            });
+        ui.end_row();
+
+        // Another shape starts from its default size.
+        if shape != primitive.shape.to_string() {
+            if let Some(value) = PrimitiveShape::all().into_iter().find(|value| value.to_string() == shape) {
+                primitive.shape = value;
+            }
+        }
+
+        match &mut primitive.shape {
+            PrimitiveShape::Cube((x, y, z)) => {
+                drag_size(ui, "size x", x);
+                drag_size(ui, "size y", y);
+                drag_size(ui, "size z", z);
+            },
+            PrimitiveShape::Sphere(radius) => {
+                drag_size(ui, "radius", radius);
+            },
+            PrimitiveShape::Plane((x, z)) => {
+                drag_size(ui, "size x", x);
+                drag_size(ui, "size z", z);
+            },
+            PrimitiveShape::Cylinder((height, radius)) | PrimitiveShape::Capsule((height, radius)) => {
+                drag_size(ui, "height", height);
+                drag_size(ui, "radius", radius);
+            },
+            PrimitiveShape::Torus((radius, ring_radius)) => {
+                drag_size(ui, "radius", radius);
+                drag_size(ui, "ring radius", ring_radius);
+            },
+        };
+
+        ui.label("collider");
+        ui.checkbox(&mut has_collider, "");
+        ui.end_row();
+
+        if has_collider && primitive.shape.to_analytic_collider().is_some() {
+            ui.label("analytic collider");
+            ui.checkbox(&mut primitive.analytic_collider, "");
+            ui.end_row();
+        }
+    });
+
+    // The same collider as "Auto generate collider" in the left panel.
+    if has_collider != object.collider.is_some() {
+        object.collider = if has_collider {
+            Some(Collider {
+                collider_type: ColliderType::FromBevyMesh,
+                collider_data: ColliderData::new(0.3, true, 1, 1, 1, 1),
+            })
+        } else {
+            None
+        };
+    }
+}
+
+// A fixed point size, changed only when dragged.
+fn drag_size(ui: &mut egui::Ui, label: &str, value: &mut u32) {
+    let mut size = *value as f32 * FROM_FLOAT;
+
+    ui.label(label);
+    if ui.add(egui::DragValue::new(&mut size).speed(0.01).clamp_range(0.001..=1000.0)).changed() {
+        *value = (size * TO_FLOAT).round() as u32;
+    }
+    ui.end_row();
+}
+
+fn show_material(ui: &mut egui::Ui, object: &mut Object, file_state: &FileState) {
+    let mut enabled = object.material.is_some();
+
+    if ui.checkbox(&mut enabled, "Material override").changed() {
+        object.material = if enabled { Some(MaterialOverride::default()) } else { None };
+    }
+
+    let material = crate::if_none_return!(object.material.as_mut());
+
+    egui::Grid::new("material").num_columns(2).show(ui, |ui| {
+        let mut base_color = material.get_base_color().as_rgba_f32();
+
+        ui.label("base color");
+        if ui.color_edit_button_rgba_unmultiplied(&mut base_color).changed() {
+            material.set_base_color(Color::rgba(base_color[0], base_color[1], base_color[2], base_color[3]));
+        }
+        ui.end_row();
+
+        let mut metallic = material.get_metallic();
+
+        ui.label("metallic");
+        if ui.add(egui::Slider::new(&mut metallic, 0.0..=1.0)).changed() {
+            material.set_metallic(metallic);
+        }
+        ui.end_row();
+
+        let mut roughness = material.get_roughness();
+
+        ui.label("roughness");
+        if ui.add(egui::Slider::new(&mut roughness, 0.0..=1.0)).changed() {
+            material.set_roughness(roughness);
+        }
+        ui.end_row();
+
+        let emissive = material.get_emissive().as_rgba_f32();
+        let mut emissive_rgb = [emissive[0], emissive[1], emissive[2]];
+
+        ui.label("emissive");
+        if ui.color_edit_button_rgb(&mut emissive_rgb).changed() {
+            material.set_emissive(Color::rgba(emissive_rgb[0], emissive_rgb[1], emissive_rgb[2], emissive[3]));
+        }
+        ui.end_row();
+
+        let mut alpha_mode = material.alpha_mode.to_string();
+
+        ui.label("alpha mode");
+        egui::ComboBox::from_id_source("material_alpha_mode")
+            .selected_text(alpha_mode.clone())
+            .show_ui(ui, |ui| {
+                for value in MaterialAlphaMode::all() {
+                    ui.selectable_value(&mut alpha_mode, value.to_string(), value.to_string());
+                }
+            });
+        ui.end_row();
+
+        if alpha_mode != material.alpha_mode.to_string() {
+            if let Some(value) = MaterialAlphaMode::all().into_iter().find(|value| value.to_string() == alpha_mode) {
+                material.alpha_mode = value;
+            }
+        }
+
+        if let MaterialAlphaMode::Mask(cutoff) = &mut material.alpha_mode {
+            let mut value = *cutoff as f32 * FROM_FLOAT;
+
+            ui.label("alpha cutoff");
+            if ui.add(egui::Slider::new(&mut value, 0.0..=1.0)).changed() {
+                *cutoff = (value * TO_FLOAT).round() as u32;
+            }
+            ui.end_row();
+        }
+
+        texture_row(ui, "base color texture", &mut material.base_color_texture, file_state);
+        texture_row(ui, "metallic roughness", &mut material.metallic_roughness_texture, file_state);
+        texture_row(ui, "normal map", &mut material.normal_map_texture, file_state);
+        texture_row(ui, "emissive texture", &mut material.emissive_texture, file_state);
+    });
+}
+
+// Textures are stored with asset paths like the objects.
+fn texture_row(ui: &mut egui::Ui, label: &str, path: &mut Option<PathBuf>, file_state: &FileState) {
+    ui.label(label);
+    ui.horizontal(|ui| {
+        let name = path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map_or("none".to_string(), |name| name.to_string_lossy().to_string());
+
+        if ui.button(name).clicked() {
+            if let Some(picked) = rfd::FileDialog::new()
+                .set_directory(file_state.assets_path.as_path())
+                .add_filter("images", &TEXTURE_EXTENSIONS)
+                .pick_file()
+                .and_then(|picked| asset_path_or_warn(file_state, &picked))
+            {
+                *path = Some(picked);
+            }
+        }
+
+        if path.is_some() && ui.button("x").clicked() {
+            *path = None;
+        }
+    });
+    ui.end_row();
+}
+
+fn show_particles(ui: &mut egui::Ui, particles: &mut ParticleData) {
+    ui.label("Particles");
+
+    egui::Grid::new("particles").num_columns(2).show(ui, |ui| {
+        ui.label("capacity");
+        ui.add(egui::DragValue::new(&mut particles.capacity).speed(16.0).clamp_range(1..=1000000));
+        ui.end_row();
+
+        let mut spawn_rate = particles.get_spawn_rate();
+
+        ui.label("spawn rate");
+        if ui.add(egui::DragValue::new(&mut spawn_rate).speed(1.0).clamp_range(0.0..=100000.0)).changed() {
+            particles.set_spawn_rate(spawn_rate);
+        }
+        ui.end_row();
+
+        let mut lifetime = particles.get_lifetime();
+
+        ui.label("lifetime");
+        if ui.add(egui::DragValue::new(&mut lifetime).speed(0.05).clamp_range(0.0..=1000.0)).changed() {
+            particles.set_lifetime(lifetime);
+        }
+        ui.end_row();
+
+        let mut spawn_radius = particles.get_spawn_radius();
+
+        ui.label("spawn radius");
+        if ui.add(egui::DragValue::new(&mut spawn_radius).speed(0.01).clamp_range(0.0..=1000.0)).changed() {
+            particles.set_spawn_radius(spawn_radius);
+        }
+        ui.end_row();
+
+        let mut speed = particles.get_speed();
+
+        ui.label("speed");
+        if ui.add(egui::DragValue::new(&mut speed).speed(0.05).clamp_range(0.0..=1000.0)).changed() {
+            particles.set_speed(speed);
+        }
+        ui.end_row();
+
+        let mut acceleration = particles.get_acceleration();
+
+        ui.label("acceleration");
+        ui.horizontal(|ui| {
+            let x = ui.add(egui::DragValue::new(&mut acceleration.x).speed(0.05)).changed();
+            let y = ui.add(egui::DragValue::new(&mut acceleration.y).speed(0.05)).changed();
+            let z = ui.add(egui::DragValue::new(&mut acceleration.z).speed(0.05)).changed();
+
+            if x || y || z {
+                particles.set_acceleration(acceleration);
+            }
+        });
+        ui.end_row();
+    });
+
+    ui.label("color over lifetime");
+
+    let mut removed = None;
+
+    egui::Grid::new("particle_colors").num_columns(3).show(ui, |ui| {
+        for (index, key) in particles.color_keys.iter_mut().enumerate() {
+            let mut ratio = key.get_ratio();
+            let mut color = key.get_color().as_rgba_f32();
+
+            if ui.add(egui::Slider::new(&mut ratio, 0.0..=1.0)).changed() {
+                key.set_ratio(ratio);
+            }
+            if ui.color_edit_button_rgba_unmultiplied(&mut color).changed() {
+                key.set_color(Color::rgba(color[0], color[1], color[2], color[3]));
+            }
+            if ui.button("x").clicked() {
+                removed = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(index) = removed {
+        particles.color_keys.remove(index);
+    }
+
+    if ui.button("add color key").clicked() {
+        particles.color_keys.push(ParticleColorKey::new(1.0, Color::WHITE));
+    }
+
+    ui.label("size over lifetime");
+
+    let mut removed = None;
+
+    egui::Grid::new("particle_sizes").num_columns(3).show(ui, |ui| {
+        for (index, key) in particles.size_keys.iter_mut().enumerate() {
+            let mut ratio = key.get_ratio();
+            let mut size = key.get_size();
+
+            if ui.add(egui::Slider::new(&mut ratio, 0.0..=1.0)).changed() {
+                key.set_ratio(ratio);
+            }
+            if ui.add(egui::DragValue::new(&mut size).speed(0.01).clamp_range(0.0..=100.0)).changed() {
+                key.set_size(size);
+            }
+            if ui.button("x").clicked() {
+                removed = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(index) = removed {
+        particles.size_keys.remove(index);
+    }
+
+    if ui.button("add size key").clicked() {
+        particles.size_keys.push(ParticleSizeKey::new(1.0, 0.1));
+    }
+}
@@ -4,20 +4,30 @@ use bevy_egui::{egui, EguiContexts};
 use rfd::{MessageButtons, MessageLevel};
 
 use crate::{if_err_return, objects::{ObjectType, Object}, picking::PickingEvent};
+use crate::objects::{PrefabInstance, PrefabNode, EditorIdMap, ApplyPrefabEvent, RevertPrefabEvent, CreatePrefabEvent, SaveRonEvent, prefab_instance_entity};
 
 use super::{FileState, SelectState};
+use super::left_panel::asset_path_or_warn;
+use super::object_panel::show_object_properties;
 
 
 pub fn process_right_panel (
     mut commands: Commands,
-  //  file_state: Res<FileState>,
+    file_state: Res<FileState>,
     mut select_state: ResMut<SelectState>,
     mut contexts: EguiContexts,
     transform_query: Query<&Transform, With<Object>>,
     root_query: Query<Entity, Without<Parent>>,
     object_query: Query<&Object>,
     children_query: Query<&Children, With<Object>>,
+    instance_query: Query<&PrefabInstance>,
+    prefab_query: Query<&PrefabNode>,
+    id_map: Res<EditorIdMap>,
     mut picking_writer: EventWriter<PickingEvent>,
+    mut apply_writer: EventWriter<ApplyPrefabEvent>,
+    mut revert_writer: EventWriter<RevertPrefabEvent>,
+    mut save_writer: EventWriter<SaveRonEvent>,
+    mut create_prefab_writer: EventWriter<CreatePrefabEvent>,
 ) {
     let ctx = contexts.ctx_mut();
 
@@ -71,6 +81,63 @@ pub fn process_right_panel (
 
                             entity_commands.insert(transform);
                         }
+
+                        if let Ok(object) = object_query.get(selected_entity) {
+                            if let Some(object) = show_object_properties(ui, object, &file_state) {
+                                commands.entity(selected_entity).insert(object);
+                            }
+                        }
+
+                        // Nodes of an instance belong to their prefab file.
+                        if !prefab_query.contains(selected_entity) && ui.button("Create prefab from selection").clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .set_directory(file_state.assets_path.as_path())
+                                .add_filter(".ron", &["ron"])
+                                .save_file()
+                                .filter(|path| asset_path_or_warn(&file_state, path).is_some())
+                            {
+                                save_writer.send(SaveRonEvent { path: Some(path.clone()), root: Some(selected_entity), snapshot: false });
+                                create_prefab_writer.send(CreatePrefabEvent { entity: selected_entity, path });
+                            }
+                        }
+
+                        if let Some(instance) = prefab_instance_entity(selected_entity, &instance_query, &prefab_query, &id_map) {
+                            let name = object_query
+                                .get(instance)
+                                .ok()
+                                .and_then(|object| object.path.as_ref())
+                                .map_or("-".to_string(), |path| path.display().to_string());
+
+                            ui.separator();
+                            ui.label("Prefab: ".to_string() + &name);
+
+                            ui.horizontal(|ui| {
+                                if ui.button("Apply overrides to prefab").clicked() {
+                                    if rfd::MessageDialog::new()
+                                        .set_level(MessageLevel::Warning)
+                                        .set_title("Apply overrides")
+                                        .set_description(&format!("Do you want to save the changes of this instance to {}?", name))
+                                        .set_buttons(MessageButtons::YesNo)
+                                        .show()
+                                    {
+                                        apply_writer.send(ApplyPrefabEvent { entity: instance });
+                                    }
+                                }
+
+                                if ui.button("Revert to prefab").clicked() {
+                                    if rfd::MessageDialog::new()
+                                        .set_level(MessageLevel::Warning)
+                                        .set_title("Revert to prefab")
+                                        .set_description("Do you want to discard the changes of this instance?")
+                                        .set_buttons(MessageButtons::YesNo)
+                                        .show()
+                                    {
+                                        revert_writer.send(RevertPrefabEvent { entity: instance });
+                                        picking_writer.send(PickingEvent { entity: instance });
+                                    }
+                                }
+                            });
+                        }
                     }
                 });
 
@@ -111,6 +178,10 @@ fn show_node (
                     ObjectType::Mesh => "GLTF Mesh",
                     ObjectType::Ron => "Ron",
                     ObjectType::Collider => "Collider",
+                    ObjectType::Node => "GLTF Node",
+                    ObjectType::Light => "Light",
+                    ObjectType::Primitive => "Primitive",
+                    ObjectType::Particles => "Particles",
                 };
 
                 if ui.button(name).clicked() {
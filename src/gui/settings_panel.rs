@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::objects::LevelSettings;
+
+
+#[derive(Default, Resource)]
+pub struct SettingsPanelState {
+    pub open: bool,
+}
+
+pub fn process_settings_panel (
+    mut panel_state: ResMut<SettingsPanelState>,
+    mut settings: ResMut<LevelSettings>,
+    mut contexts: EguiContexts,
+) {
+    if !panel_state.open {
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+
+    let mut open = panel_state.open;
+    // Edited on a copy, so the settings are applied again only when they change.
+    let mut edited = settings.clone();
+
+    egui::Window::new("Level settings")
+        .open(&mut open)
+        .default_width(300.0)
+        .show(ctx, |ui| {
+            egui::Grid::new("level_settings").num_columns(2).show(ui, |ui| {
+                ui.label("Author");
+                ui.text_edit_singleline(&mut edited.author);
+                ui.end_row();
+
+                ui.label("Description");
+                ui.text_edit_multiline(&mut edited.description);
+                ui.end_row();
+
+                ui.label("Ambient light");
+                ui.color_edit_button_rgba_unmultiplied(&mut edited.ambient_color);
+                ui.end_row();
+
+                ui.label("Ambient brightness");
+                ui.add(egui::DragValue::new(&mut edited.ambient_brightness).speed(0.01).clamp_range(0.0..=10.0));
+                ui.end_row();
+
+                ui.label("Clear color");
+                ui.color_edit_button_rgba_unmultiplied(&mut edited.clear_color);
+                ui.end_row();
+
+                ui.label("Gravity");
+                ui.horizontal(|ui| {
+                    for value in edited.gravity.iter_mut() {
+                        ui.add(egui::DragValue::new(value).speed(0.1));
+                    }
+                });
+                ui.end_row();
+            });
+
+            if ui.button("Reset").clicked() {
+                edited = LevelSettings::default();
+            }
+        });
+
+    if edited != *settings {
+        *settings = edited;
+    }
+
+    panel_state.open = open;
+}
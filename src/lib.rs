@@ -14,10 +14,21 @@ use bevy_prototype_debug_lines::DebugLinesPlugin;
 use bevy_rapier3d::prelude::*;
 use bevy_transform_gizmo::TransformGizmoPlugin;
 use bevy_debug_grid::*;
+use bevy_hanabi::HanabiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
 use crate::editor::MyEditorPlugin;
 
+pub use crate::objects::{
+    Ron, RonNode, RonTransform, RonComponent, RonComponentValue, RonLoader, RonMigrations, RonError, RonIssue,
+    RON_VERSION, serialize_ron, serialize_binary, deserialize_binary, serialize_level, BINARY_EXTENSION,
+    AutosaveSettings, Object, ObjectType, EditorId, SavedComponents,
+    RonOverride, PrefabInstance, PrefabNode, LevelSettings,
+    LevelObject, LevelObjectPlugin, LevelAssetRoots, AssetRoot, ASSET_ROOTS_FILE, read_asset_roots, to_asset_path, to_file_path,
+    ObjectImporter, ObjectImporters, ImportedAsset, ImportObjectEvent,
+};
+pub use crate::gui::MyEditorState;
+
 pub struct AplicationPlugin;
 
 impl Plugin for AplicationPlugin {
@@ -31,6 +42,7 @@ impl Plugin for AplicationPlugin {
             .add_plugin(DebugGridPlugin::with_floor_grid())
             .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
             .add_plugin(RapierDebugRenderPlugin::default())
+            .add_plugin(HanabiPlugin)
             .add_plugin(MyEditorPlugin);
     }
 }
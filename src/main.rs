@@ -16,7 +16,6 @@ fn main() {
 
     app
         .insert_resource(Msaa::default())
-        .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some( Window {
                 title: "Editor".to_string(), // ToDo
@@ -25,6 +24,10 @@ fn main() {
                 ..default()
             }),
             ..default()
+        }).set(AssetPlugin {
+            // Prefab instances are rebuilt when their files change.
+            watch_for_changes: true,
+            ..default()
         }))
         .add_plugin(AplicationPlugin);
 
@@ -0,0 +1,184 @@
+use std::path::{Component, Path, PathBuf};
+use bevy::log;
+use ::serde::{Serialize, Deserialize};
+
+// Object paths are relative to the default assets folder, or `name://relative/path`
+// for a named root.
+pub const ROOT_SEPARATOR: &str = "://";
+
+// The named roots of a project in the assets folder, like `[(name: "shared", path: "../shared")]`.
+// Read by the editor, `level_tool` and `LevelObjectPlugin`, so that all of them resolve the same paths.
+pub const ASSET_ROOTS_FILE: &str = "asset_roots.ron";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetRoot {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl AssetRoot {
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+        }
+    }
+}
+
+// Relative root paths are relative to the assets folder, no file means no named roots.
+pub fn read_asset_roots(assets_path: &Path) -> Result<Vec<AssetRoot>, String> {
+    let path = assets_path.join(ASSET_ROOTS_FILE);
+
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let serialized = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let roots: Vec<AssetRoot> = ron::de::from_str(&serialized).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    Ok(roots
+        .into_iter()
+        .map(|root| {
+            AssetRoot::new(root.name, normalize_path(&assets_path.join(&root.path)))
+        })
+        .collect())
+}
+
+// Splits `name://relative` into the root name and the relative part.
+pub fn split_root(path: &Path) -> (Option<String>, PathBuf) {
+    let path_str = path.to_string_lossy();
+
+    match path_str.split_once(ROOT_SEPARATOR) {
+        Some((name, relative)) => (Some(name.to_string()), PathBuf::from(relative)),
+        None => (None, path.to_path_buf()),
+    }
+}
+
+// The most specific root containing the file wins, `None` if the file is outside every root.
+pub fn to_asset_path(assets_path: &Path, roots: &[AssetRoot], path: &Path) -> Option<PathBuf> {
+    let mut best: Option<(usize, PathBuf)> = None;
+
+    let path = normalize_path(path);
+    let assets_path = normalize_path(assets_path);
+
+    if let Ok(relative) = path.strip_prefix(&assets_path) {
+        best = Some((assets_path.components().count(), portable(relative)));
+    }
+
+    for root in roots.iter() {
+        let root_path = normalize_path(&root.path);
+
+        let relative = match path.strip_prefix(&root_path) {
+            Ok(relative) => relative,
+            Err(_) => continue,
+        };
+
+        let depth = root_path.components().count();
+
+        if best.as_ref().map_or(true, |(best_depth, _)| depth > *best_depth) {
+            let asset_path = format!("{}{}{}", root.name, ROOT_SEPARATOR, portable(relative).display());
+            best = Some((depth, PathBuf::from(asset_path)));
+        }
+    }
+
+    best.map(|(_, asset_path)| asset_path)
+}
+
+// Path on disk, for tools that read the files directly.
+pub fn to_file_path(assets_path: &Path, roots: &[AssetRoot], path: &Path) -> Option<PathBuf> {
+    match split_root(path) {
+        (Some(name), relative) => roots
+            .iter()
+            .find(|root| root.name == name)
+            .map(|root| root.path.join(relative)),
+        (None, relative) => Some(assets_path.join(relative)),
+    }
+}
+
+// Path for `AssetServer::load`. Default root paths stay relative to the asset server folder,
+// named roots become absolute.
+pub fn to_load_path(roots: &[AssetRoot], path: &Path) -> String {
+    match split_root(path) {
+        (Some(name), relative) => match roots.iter().find(|root| root.name == name) {
+            Some(root) => root.path.join(relative).display().to_string(),
+            None => {
+                log::error!("to_load_path: unknown asset root {} in {}", name, path.display());
+                path.display().to_string()
+            }
+        },
+        (None, relative) => relative.display().to_string(),
+    }
+}
+
+// Removes `.` and `..` without reading the file system, so the roots and the compared paths
+// are cleaned the same way. Symlinks are kept and Windows paths don't get the `\\?\` prefix.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                },
+                // `..` of the root is the root.
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => (),
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+
+    normalized
+}
+
+// Levels are shared between systems, so separators are always `/`.
+fn portable(path: &Path) -> PathBuf {
+    let parts: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+
+    PathBuf::from(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_path_removes_dots() {
+        assert_eq!(normalize_path(Path::new("/project/assets/./levels/../shared")), PathBuf::from("/project/assets/shared"));
+        assert_eq!(normalize_path(Path::new("../shared/./rock.glb")), PathBuf::from("../shared/rock.glb"));
+        assert_eq!(normalize_path(Path::new("/..")), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn relative_root_matches_paths() {
+        let project = std::env::temp_dir().join(format!("kns_asset_roots_{}", std::process::id()));
+        let assets_path = project.join("assets");
+
+        std::fs::create_dir_all(&assets_path).unwrap();
+        std::fs::write(assets_path.join(ASSET_ROOTS_FILE), r#"[(name: "shared", path: "../shared")]"#).unwrap();
+
+        let roots = read_asset_roots(&assets_path);
+        std::fs::remove_dir_all(&project).unwrap();
+
+        let roots = roots.unwrap();
+        assert_eq!(roots, vec![AssetRoot::new("shared", project.join("shared"))]);
+
+        // The root folder doesn't exist, paths still match as they are written.
+        let rock = assets_path.join("../shared/models/./rock.glb");
+        assert_eq!(to_asset_path(&assets_path, &roots, &rock), Some(PathBuf::from("shared://models/rock.glb")));
+
+        let level = assets_path.join("./levels/a.ron");
+        assert_eq!(to_asset_path(&assets_path, &roots, &level), Some(PathBuf::from("levels/a.ron")));
+
+        let outside = project.join("other/a.ron");
+        assert_eq!(to_asset_path(&assets_path, &roots, &outside), None);
+    }
+}
@@ -14,8 +14,8 @@ use bevy::reflect::{
 use bevy::utils::HashMap;
 //use bevy_picking_rapier::RapierPickTarget;
 
-const TO_FLOAT: f32 = 10000.0;
-const FROM_FLOAT: f32 = 0.0001;
+pub(crate) const TO_FLOAT: f32 = 10000.0;
+pub(crate) const FROM_FLOAT: f32 = 0.0001;
 
 #[derive(
     Default,
@@ -24,6 +24,8 @@ const FROM_FLOAT: f32 = 0.0001;
     Eq,
     PartialEq,
     Hash,
+    PartialOrd,
+    Ord,
     Resource,
     Component,
     Reflect,
@@ -66,6 +68,8 @@ impl ToString for ColliderType {
     Eq,
     PartialEq,
     Hash,
+    PartialOrd,
+    Ord,
     Resource,
     Component,
     Reflect,
@@ -113,6 +117,8 @@ impl ColliderData {
     Eq,
     PartialEq,
     Hash,
+    PartialOrd,
+    Ord,
     Resource,
     Component,
     Reflect,
@@ -233,7 +239,7 @@ fn process_create_collider(
     }
 }
 
-fn aply_collider_data(commands: &mut Commands, entity: &Entity, collider_data: &ColliderData) {
+pub(crate) fn aply_collider_data(commands: &mut Commands, entity: &Entity, collider_data: &ColliderData) {
     log::info!("aply_collider_data");
 
     let mut entity_commands = crate::if_none_return!(commands.get_entity(*entity));
@@ -263,24 +269,44 @@ pub fn add_collider_from_mesh(
     mesh: &Mesh,
     collider_data: &ColliderData,
 ) {
-    log::info!("add_collider_from_mesh");
-
-    let mut entity_commands = crate::if_none_return!(commands.get_entity(*entity));
+    add_collider_from_meshes(commands, entity, &[(Transform::IDENTITY, mesh)], collider_data);
+}
 
-    if mesh.count_vertices() <= 0 {
-        return;
-    }
+// All primitives of a gltf mesh or node share one collider, a compound of their trimeshes
+// placed relative to the entity. The scale of the parts is not applied.
+pub fn add_collider_from_meshes(
+    commands: &mut Commands,
+    entity: &Entity,
+    meshes: &[(Transform, &Mesh)],
+    collider_data: &ColliderData,
+) {
+    log::info!("add_collider_from_meshes");
 
-    if let Some(collider) = bevy_rapier3d::prelude::Collider::from_bevy_mesh(
-        mesh,
-        &bevy_rapier3d::prelude::ComputedColliderShape::TriMesh,
-    ) {
-        entity_commands.insert(collider);
+    let mut entity_commands = crate::if_none_return!(commands.get_entity(*entity));
 
-        log::info!("add_collider_from_mesh ok");
-    } else {
-        return;
-    }
+    let mut colliders: Vec<(Transform, bevy_rapier3d::prelude::Collider)> = meshes
+        .iter()
+        .filter(|(_, mesh)| mesh.count_vertices() > 0)
+        .filter_map(|(transform, mesh)| bevy_rapier3d::prelude::Collider::from_bevy_mesh(
+            mesh,
+            &bevy_rapier3d::prelude::ComputedColliderShape::TriMesh,
+        ).map(|collider| (*transform, collider)))
+        .collect();
+
+    let collider = match colliders.len() {
+        0 => return,
+        1 if colliders[0].0 == Transform::IDENTITY => colliders.remove(0).1,
+        _ => bevy_rapier3d::prelude::Collider::compound(
+            colliders
+                .into_iter()
+                .map(|(transform, collider)| (transform.translation, transform.rotation, collider))
+                .collect(),
+        ),
+    };
+
+    entity_commands.insert(collider);
+
+    log::info!("add_collider_from_meshes ok");
 
     aply_collider_data(commands, entity, collider_data);
 }
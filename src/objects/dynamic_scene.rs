@@ -0,0 +1,581 @@
+use std::any::type_name;
+use std::path::{Path, PathBuf};
+use bevy::asset::{FileAssetIo, LoadState};
+use bevy::reflect::{DynamicList, DynamicTupleStruct, ReflectRef};
+use bevy::scene::{DynamicEntity, DynamicScene, serde::SceneDeserializer};
+use bevy::{log, prelude::*, utils::HashMap};
+use bevy_gltf::{GltfMesh, GltfNode};
+use bevy_hanabi::{EffectAsset, HanabiPlugin};
+use ::serde::{Serialize, Deserialize, de::DeserializeSeed};
+
+use crate::gui::FileState;
+use crate::{if_none_return, if_none_continue};
+use super::{to_load_path, read_asset_roots, AssetRoot, AddObjectEvent, Object, ObjectType, ObjectImporters, EditorId, ColliderType, ColliderPlugin, CreateColliderEvent, add_collider_from_meshes, insert_light_components, insert_primitive, spawn_particle_effect};
+use super::gltf::{spawn_node, spawn_primitives};
+use super::ron::{reflect_components, InsertReflectComponents};
+
+// The editor object on entities of exported scenes, the path is an asset path (see `to_asset_path`).
+// `LevelObjectPlugin` is required to spawn these entities in a game: scene files store asset
+// handles as ids without their path, so a `Handle<Scene>` in the file loads nothing. The plugin
+// loads the assets from this path, resolves named roots from `ASSET_ROOTS_FILE` or
+// `LevelAssetRoots`, and adds the meshes, lights, particles, materials and colliders.
+#[derive(Default, Debug, Clone, Component, Reflect, FromReflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct LevelObject {
+    pub object: Object,
+}
+
+pub struct ImportSceneEvent {
+    pub path: PathBuf,
+}
+
+pub struct ExportSceneEvent {
+    pub path: PathBuf,
+    pub root: Option<Entity>,
+}
+
+#[derive(Default, Resource)]
+pub(crate) struct PendingScenes {
+    scenes: Vec<(DynamicScene, Vec<HandleUntyped>)>,
+}
+
+pub(crate) fn process_export_scene(
+    world: &World,
+    mut reader: EventReader<ExportSceneEvent>,
+    root_query: Query<(Entity, &EditorId), (With<Object>, Without<Parent>)>,
+) {
+    let ExportSceneEvent { path, root } = if_none_return!(reader.iter().last());
+
+    log::info!("process_export_scene {:?}", path);
+
+    let roots = if let Some(root) = root {
+        vec![*root]
+    } else {
+        let mut roots: Vec<(Entity, &EditorId)> = root_query.iter().collect();
+        roots.sort_by(|a, b| a.1.cmp(b.1));
+        roots.into_iter().map(|(entity, _)| entity).collect()
+    };
+
+    let mut entities = Vec::new();
+
+    for root in roots.into_iter() {
+        collect_objects(world, root, &mut entities);
+    }
+
+    let indices: HashMap<Entity, u32> = entities
+        .iter()
+        .enumerate()
+        .map(|(index, entity)| (*entity, index as u32))
+        .collect();
+
+    let scene = DynamicScene {
+        entities: entities
+            .iter()
+            .map(|entity| create_scene_entity(world, *entity, &indices))
+            .collect(),
+    };
+
+    let result = scene
+        .serialize_ron(&world.resource::<AppTypeRegistry>().0)
+        .map_err(|e| e.to_string())
+        .and_then(|serialized| std::fs::write(path, serialized).map_err(|e| e.to_string()));
+
+    if let Err(e) = result {
+        log::error!("process_export_scene: {}", e);
+
+        rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Error)
+            .set_title("Export scene error")
+            .set_description(&format!("Failed to export {}: {}", path.display(), e))
+            .set_buttons(rfd::MessageButtons::Ok)
+            .show();
+    }
+}
+
+// Nested ron objects are exported as their spawned objects, scene instances and
+// editor helpers are left out.
+fn collect_objects(world: &World, entity: Entity, entities: &mut Vec<Entity>) {
+    if world.get::<Object>(entity).is_none() {
+        return;
+    }
+
+    entities.push(entity);
+
+    if let Some(children) = world.get::<Children>(entity) {
+        for child in children.iter() {
+            collect_objects(world, *child, entities);
+        }
+    }
+}
+
+fn create_scene_entity(
+    world: &World,
+    entity: Entity,
+    indices: &HashMap<Entity, u32>,
+) -> DynamicEntity {
+    let mut components: Vec<Box<dyn Reflect>> = vec![
+        Box::new(world.get::<Transform>(entity).copied().unwrap_or_default()),
+        Box::new(GlobalTransform::default()),
+        Box::new(world.get::<Visibility>(entity).copied().unwrap_or_default()),
+        Box::new(ComputedVisibility::default()),
+    ];
+
+    if let Some(object) = world.get::<Object>(entity) {
+        match object.object_type {
+            ObjectType::Scene | ObjectType::Mesh | ObjectType::Node | ObjectType::Light | ObjectType::Primitive | ObjectType::Particles | ObjectType::Collider => {
+                components.push(Box::new(LevelObject { object: object.clone() }));
+            },
+            ObjectType::Ron | ObjectType::Empty => (),
+        };
+    }
+
+    // Scene entity ids are indices, so hierarchy components are rebuilt to refer to them.
+    if let Some(parent) = world.get::<Parent>(entity) {
+        if let Some(index) = indices.get(&parent.get()) {
+            let mut parent = DynamicTupleStruct::default();
+            parent.set_name(type_name::<Parent>().to_string());
+            parent.insert(Entity::from_raw(*index));
+
+            components.push(Box::new(parent));
+        }
+    }
+
+    if let Some(children) = world.get::<Children>(entity) {
+        let mut list = DynamicList::default();
+
+        if let ReflectRef::TupleStruct(children) = children.reflect_ref() {
+            if let Some(field) = children.field(0) {
+                list.set_name(field.type_name().to_string());
+            }
+        }
+
+        let indices: Vec<u32> = children
+            .iter()
+            .filter_map(|child| indices.get(child).copied())
+            .collect();
+
+        if !indices.is_empty() {
+            for index in indices.into_iter() {
+                list.push(Entity::from_raw(index));
+            }
+
+            let mut children = DynamicTupleStruct::default();
+            children.set_name(type_name::<Children>().to_string());
+            children.insert(list);
+
+            components.push(Box::new(children));
+        }
+    }
+
+    components.append(&mut reflect_components(world, entity));
+
+    DynamicEntity {
+        entity: indices[&entity],
+        components,
+    }
+}
+
+// Assets of the scene objects are loaded before spawning, like with ron levels.
+pub(crate) fn process_import_scene(
+    mut reader: EventReader<ImportSceneEvent>,
+    mut pending: ResMut<PendingScenes>,
+    type_registry: Res<AppTypeRegistry>,
+    file_state: Res<FileState>,
+    asset_server: Res<AssetServer>,
+) {
+    for ImportSceneEvent { path } in reader.iter() {
+        log::info!("process_import_scene {:?}", path);
+
+        let scene = match read_scene(path, &type_registry) {
+            Ok(scene) => scene,
+            Err(e) => {
+                log::error!("process_import_scene: {}", e);
+
+                rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Error)
+                    .set_title("Import scene error")
+                    .set_description(&format!("Failed to import {}: {}", path.display(), e))
+                    .set_buttons(rfd::MessageButtons::Ok)
+                    .show();
+
+                continue;
+            }
+        };
+
+        let mut handles = Vec::new();
+
+        for scene_entity in scene.entities.iter() {
+            for component in scene_entity.components.iter() {
+                if component.type_name() != type_name::<LevelObject>() {
+                    continue;
+                }
+
+                let level_object = if_none_continue!(LevelObject::from_reflect(&**component));
+
+                if let Some(path) = &level_object.object.path {
+                    handles.push(asset_server.load_untyped(file_state.to_load_path(path)));
+                }
+            }
+        }
+
+        pending.scenes.push((scene, handles));
+    }
+}
+
+// Failed assets don't stop the scene, the objects report them as ron levels do.
+pub(crate) fn spawn_imported_scenes(
+    mut commands: Commands,
+    mut pending: ResMut<PendingScenes>,
+    asset_server: Res<AssetServer>,
+    mut writer: EventWriter<AddObjectEvent>,
+) {
+    if pending.scenes.is_empty() {
+        return;
+    }
+
+    let scenes = std::mem::take(&mut pending.scenes);
+
+    for (scene, handles) in scenes.into_iter() {
+        let loaded = handles.iter().all(|handle| matches!(
+            asset_server.get_load_state(handle),
+            LoadState::Loaded | LoadState::Failed,
+        ));
+
+        if !loaded {
+            pending.scenes.push((scene, handles));
+            continue;
+        }
+
+        log::info!("spawn_imported_scenes");
+
+        let entities: HashMap<u32, Entity> = scene.entities
+            .iter()
+            .map(|scene_entity| (scene_entity.entity, commands.spawn_empty().id()))
+            .collect();
+
+        for scene_entity in scene.entities.iter() {
+            spawn_scene_entity(&mut commands, scene_entity, &entities, &mut writer);
+        }
+    }
+}
+
+fn spawn_scene_entity(
+    commands: &mut Commands,
+    scene_entity: &DynamicEntity,
+    entities: &HashMap<u32, Entity>,
+    writer: &mut EventWriter<AddObjectEvent>,
+) {
+    let entity = entities[&scene_entity.entity];
+
+    let mut transform = Transform::IDENTITY;
+    let mut object = Object::default();
+    let mut components = Vec::new();
+
+    for component in scene_entity.components.iter() {
+        let name = component.type_name();
+
+        if name == type_name::<Transform>() {
+            transform = Transform::from_reflect(&**component).unwrap_or_default();
+        } else if name == type_name::<LevelObject>() {
+            if let Some(level_object) = LevelObject::from_reflect(&**component) {
+                object = level_object.object;
+            }
+        } else if name == type_name::<Parent>() {
+            let parent = reflect_entity(&**component).and_then(|parent| entities.get(&parent.index()));
+
+            if let Some(parent) = parent {
+                commands.entity(*parent).add_child(entity);
+            }
+        } else if name == type_name::<Children>()
+            || name == type_name::<GlobalTransform>()
+            || name == type_name::<Visibility>()
+            || name == type_name::<ComputedVisibility>()
+        {
+            continue;
+        } else {
+            components.push(component.clone_value());
+        }
+    }
+
+    if !components.is_empty() {
+        commands.add(InsertReflectComponents {
+            entity,
+            components,
+        });
+    }
+
+    writer.send(AddObjectEvent {
+        entity: Some(entity),
+        id: None,
+        object: Some(object),
+        transform: Some(transform),
+        selected: false,
+        overrides: Vec::new(),
+    });
+}
+
+fn reflect_entity(parent: &dyn Reflect) -> Option<Entity> {
+    match parent.reflect_ref() {
+        ReflectRef::TupleStruct(parent) => parent.field(0)?.downcast_ref::<Entity>().copied(),
+        _ => None,
+    }
+}
+
+fn read_scene(path: &Path, type_registry: &AppTypeRegistry) -> Result<DynamicScene, String> {
+    let serialized = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let mut deserializer = ron::de::Deserializer::from_str(&serialized).map_err(|e| e.to_string())?;
+
+    SceneDeserializer { type_registry: &type_registry.read() }
+        .deserialize(&mut deserializer)
+        .map_err(|e| e.to_string())
+}
+
+// Named asset roots of a game, read from `ASSET_ROOTS_FILE` in its assets folder unless the game
+// inserts them before adding `LevelObjectPlugin`.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct LevelAssetRoots(pub Vec<AssetRoot>);
+
+// Loads the assets and colliders of `LevelObject`s in a game that spawns exported scenes.
+pub struct LevelObjectPlugin;
+
+impl Plugin for LevelObjectPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ColliderPlugin>() {
+            app.add_plugin(ColliderPlugin);
+        }
+
+        if !app.is_plugin_added::<HanabiPlugin>() {
+            app.add_plugin(HanabiPlugin);
+        }
+
+        if !app.world.contains_resource::<LevelAssetRoots>() {
+            let assets_path = FileAssetIo::get_base_path().join("assets");
+
+            let roots = read_asset_roots(&assets_path).unwrap_or_else(|e| {
+                log::error!("LevelObjectPlugin: failed to read the asset roots, {}", e);
+                Vec::new()
+            });
+
+            app.insert_resource(LevelAssetRoots(roots));
+        }
+
+        // Games register the importers of their formats like the editor.
+        app
+            .init_resource::<ObjectImporters>()
+            .register_type::<LevelObject>()
+            .add_systems((
+                process_add_level_object,
+                process_add_level_mesh.after(process_add_level_object),
+                process_add_level_node.after(process_add_level_object),
+                process_level_materials,
+            ));
+    }
+}
+
+fn process_add_level_object(
+    mut commands: Commands,
+    query: Query<(Entity, &LevelObject), Added<LevelObject>>,
+    asset_server: Res<AssetServer>,
+    importers: Res<ObjectImporters>,
+    roots: Res<LevelAssetRoots>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+    mut collider_writer: EventWriter<CreateColliderEvent>,
+) {
+    for (entity, LevelObject { object }) in query.iter() {
+        let path = object.path.as_ref().map(|path| to_load_path(&roots.0, path));
+
+        let label = importers.asset_label(object).map_or(String::new(), |label| "#".to_string() + &label);
+
+        // Their meshes are spawned once the assets are loaded, see `process_level_materials`.
+        if let (ObjectType::Scene | ObjectType::Mesh | ObjectType::Node, Some(material)) = (&object.object_type, object.material.as_ref()) {
+            let material = material.to_material(|path| asset_server.load(to_load_path(&roots.0, path)));
+            commands.entity(entity).insert(LevelMaterial(materials.add(material)));
+        }
+
+        match (&object.object_type, path) {
+            (ObjectType::Scene, Some(path)) => {
+                commands.entity(entity).insert(asset_server.load::<Scene, _>(path + &label));
+            },
+            (ObjectType::Mesh, Some(path)) => {
+                commands.entity(entity).insert(asset_server.load::<GltfMesh, _>(path + &label));
+                continue;
+            },
+            (ObjectType::Node, Some(path)) => {
+                commands.entity(entity).insert(asset_server.load::<GltfNode, _>(path + &label));
+                continue;
+            },
+            (ObjectType::Light, _) => {
+                if let Some(light) = object.light.as_ref() {
+                    insert_light_components(&mut commands.entity(entity), light);
+                }
+                continue;
+            },
+            (ObjectType::Primitive, _) => {
+                if let Some(primitive) = object.primitive.as_ref() {
+                    let material = object.material.as_ref().map_or(StandardMaterial::default(), |material| {
+                        material.to_material(|path| asset_server.load(to_load_path(&roots.0, path)))
+                    });
+
+                    commands.entity(entity).insert(materials.add(material));
+                    insert_primitive(&mut commands, entity, primitive, object.collider.as_ref(), &mut meshes);
+                }
+                continue;
+            },
+            (ObjectType::Particles, _) => {
+                if let Some(particles) = object.particles.as_ref() {
+                    spawn_particle_effect(&mut commands, entity, particles, &mut effects);
+                }
+                continue;
+            },
+            (ObjectType::Collider, _) => (),
+            _ => continue,
+        };
+
+        let collider = if_none_continue!(object.collider.clone());
+
+        if collider.collider_type == ColliderType::FromBevyMesh {
+            log::warn!("process_add_level_object: mesh colliders of scenes are not supported");
+            continue;
+        }
+
+        collider_writer.send(CreateColliderEvent {
+            entity,
+            collider,
+            transform: None,
+        });
+    }
+}
+
+// The material override of a scene, mesh or node object, shared by all of its meshes.
+#[derive(Component)]
+struct LevelMaterial(Handle<StandardMaterial>);
+
+// Like the editor, the nearest level object owns the mesh, so nested objects keep their own materials.
+fn process_level_materials(
+    mut commands: Commands,
+    mesh_query: Query<(Entity, &Handle<StandardMaterial>), Added<Handle<StandardMaterial>>>,
+    parent_query: Query<&Parent>,
+    object_query: Query<Option<&LevelMaterial>, With<LevelObject>>,
+) {
+    for (mesh, material) in mesh_query.iter() {
+        let mut current = mesh;
+
+        while !object_query.contains(current) {
+            current = match parent_query.get(current) {
+                Ok(parent) => parent.get(),
+                Err(_) => break,
+            };
+        }
+
+        if let Ok(Some(LevelMaterial(handle))) = object_query.get(current) {
+            if material != handle {
+                commands.entity(mesh).insert(handle.clone());
+            }
+        }
+    }
+}
+
+fn process_add_level_mesh(
+    mut commands: Commands,
+    query: Query<(Entity, &LevelObject, &Handle<GltfMesh>), Without<Handle<Mesh>>>,
+    transform_query: Query<&Transform>,
+    gltf_meshes: Res<Assets<GltfMesh>>,
+    meshes: Res<Assets<Mesh>>,
+    mut collider_writer: EventWriter<CreateColliderEvent>,
+) {
+    for (entity, LevelObject { object }, handle) in query.iter() {
+        let gltf_mesh = if_none_continue!(gltf_meshes.get(handle));
+        let transform = transform_query.get(entity).copied().unwrap_or_default();
+
+        if !spawn_primitives(&mut commands, entity, gltf_mesh, transform) {
+            continue;
+        }
+
+        let collider = if_none_continue!(object.collider.as_ref());
+
+        if collider.collider_type == ColliderType::FromBevyMesh {
+            let primitive_meshes: Vec<(Transform, &Mesh)> = gltf_mesh.primitives
+                .iter()
+                .filter_map(|primitive| meshes.get(&primitive.mesh))
+                .map(|mesh| (Transform::IDENTITY, mesh))
+                .collect();
+
+            add_collider_from_meshes(&mut commands, &entity, &primitive_meshes, &collider.collider_data);
+        } else {
+            collider_writer.send(CreateColliderEvent {
+                entity,
+                collider: collider.clone(),
+                transform: None,
+            });
+        }
+    }
+}
+
+fn process_add_level_node(
+    mut commands: Commands,
+    query: Query<(Entity, &LevelObject, &Handle<GltfNode>, &Transform)>,
+    gltf_nodes: Res<Assets<GltfNode>>,
+    gltf_meshes: Res<Assets<GltfMesh>>,
+    meshes: Res<Assets<Mesh>>,
+    mut collider_writer: EventWriter<CreateColliderEvent>,
+) {
+    for (entity, LevelObject { object }, handle, transform) in query.iter() {
+        let node = if_none_continue!(gltf_nodes.get(handle));
+
+        let mut parts = Vec::new();
+
+        // Spawned once, the handle is not needed any more.
+        spawn_node(&mut commands, entity, node, *transform, Transform::IDENTITY, &gltf_meshes, &mut parts);
+        commands.entity(entity).remove::<Handle<GltfNode>>();
+
+        let collider = if_none_continue!(object.collider.as_ref());
+
+        if collider.collider_type == ColliderType::FromBevyMesh {
+            let part_meshes: Vec<(Transform, &Mesh)> = parts
+                .iter()
+                .filter_map(|(offset, handle)| meshes.get(handle).map(|mesh| (*offset, mesh)))
+                .collect();
+
+            add_collider_from_meshes(&mut commands, &entity, &part_meshes, &collider.collider_data);
+        } else {
+            collider_writer.send(CreateColliderEvent {
+                entity,
+                collider: collider.clone(),
+                transform: None,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::HandleId;
+
+    #[test]
+    fn level_material_skips_nested_objects() {
+        let mut app = App::new();
+        app.add_system(process_level_materials);
+
+        let material: Handle<StandardMaterial> = Handle::weak(HandleId::random::<StandardMaterial>());
+        let original: Handle<StandardMaterial> = Handle::weak(HandleId::random::<StandardMaterial>());
+
+        let root = app.world.spawn((LevelObject::default(), LevelMaterial(material.clone()))).id();
+        app.update();
+
+        // Spawned later, like the meshes of a loaded scene.
+        let nested = app.world.spawn(LevelObject::default()).id();
+        let mesh = app.world.spawn(original.clone()).id();
+        let nested_mesh = app.world.spawn(original.clone()).id();
+
+        app.world.entity_mut(root).push_children(&[mesh, nested]);
+        app.world.entity_mut(nested).push_children(&[nested_mesh]);
+        app.update();
+
+        assert_eq!(app.world.get::<Handle<StandardMaterial>>(mesh), Some(&material));
+        assert_eq!(app.world.get::<Handle<StandardMaterial>>(nested_mesh), Some(&original));
+    }
+}
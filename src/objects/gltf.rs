@@ -1,8 +1,12 @@
 use bevy::{log, prelude::*};
-use bevy_gltf::{GltfMesh, GltfNode};
+use std::path::PathBuf;
+use bevy::asset::LoadState;
+use bevy::utils::HashMap;
+use bevy_gltf::{Gltf, GltfMesh, GltfNode};
 use bevy_mod_picking::prelude::{OnPointer, Click, ListenedEvent, Bubble};
 
-use crate::if_none_return;
+use crate::if_none_continue;
+use crate::gui::{FileState, MyEditorState};
 use rfd::*;
 
 use super::*;
@@ -48,12 +52,75 @@ pub fn process_add_gltf_scene(
     }
 }
 
+// Primitives after the first one of a gltf mesh, children of the mesh object.
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub struct MeshPrimitive;
+
+// The first primitive is on the object itself, the others are spawned as its children
+// with their own materials. Returns false if the mesh has no primitives.
+pub(crate) fn spawn_primitives(
+    commands: &mut Commands,
+    entity: Entity,
+    gltf_mesh: &GltfMesh,
+    transform: Transform,
+) -> bool {
+    let (first, others) = match gltf_mesh.primitives.split_first() {
+        Some(split) => split,
+        None => return false,
+    };
+
+    let mut entity_commands = match commands.get_entity(entity) {
+        Some(entity_commands) => entity_commands,
+        None => return false,
+    };
+
+    entity_commands.insert(PbrBundle {
+        mesh: first.mesh.clone(),
+        material: first.material.clone().unwrap_or_default(),
+        transform,
+        ..default()
+    });
+
+    entity_commands.with_children(|parent| {
+        for (index, primitive) in others.iter().enumerate() {
+            parent.spawn((
+                PbrBundle {
+                    mesh: primitive.mesh.clone(),
+                    material: primitive.material.clone().unwrap_or_default(),
+                    ..default()
+                },
+                Name::new(format!("primitive {}", index + 1)),
+                MeshPrimitive,
+            ));
+        }
+    });
+
+    true
+}
+
+// The object is added again on relink or rebuild, its old primitives are replaced.
+fn despawn_primitives(
+    commands: &mut Commands,
+    entity: Entity,
+    children_query: &Query<&Children>,
+    primitive_query: &Query<(), With<MeshPrimitive>>,
+) {
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter() {
+            if primitive_query.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+    }
+}
+
 pub fn process_add_gltf_mesh(
     mut commands: Commands,
     mut reader: EventReader<AddGltfMeshEvent>,
-    //    gltf_nodes: Res<Assets<GltfNode>>,
     gltf_meshes: Res<Assets<GltfMesh>>,
     meshes: Res<Assets<Mesh>>,
+    children_query: Query<&Children>,
+    primitive_query: Query<(), With<MeshPrimitive>>,
     mut collider_writer: EventWriter<CreateColliderEvent>,
 ) {
     for AddGltfMeshEvent {
@@ -65,63 +132,234 @@ pub fn process_add_gltf_mesh(
     {
         log::info!("process_add_gltf_mesh");        
 
-        if let Some(primitive) = gltf_meshes.get(&handle) {
-            if let Some(primitive) = primitive.primitives.first() {
-
-                if let Some(mut entity_commands) = commands.get_entity(*entity) {
-                    if let Some(material) = primitive.material.clone() {
-                        entity_commands.insert((PbrBundle {
-                            mesh: primitive.mesh.clone(),
-                            transform: *transform,
-                            material,
-                            ..default()
-                        },));
-                    } else {
-                        entity_commands.insert((PbrBundle {
-                            mesh: primitive.mesh.clone(),
-                            transform: *transform,
-                            ..default()
-                        },));
-                    };
-                } else {
-                    continue;
-                }
-
-
-                if let Some(collider) = collider {
-                    log::info!("process_add_gltf_mesh collider");
-
-                    collider_writer.send(CreateColliderEvent {
-                        entity: *entity,
-                        collider: collider.to_owned(),
-                        transform: None,
-                    });
-/* 
-                    log::info!("process_add_gltf_mesh collider");
-
-                    collider::add_collider_from_mesh(
-                        &mut commands,
-                        entity,
-                        &primitive.mesh,
-                        &meshes,
-                        &collider.collider_data,
-                    ); */
-                }
-            } else {
+        let gltf_mesh = match gltf_meshes.get(&handle) {
+            Some(gltf_mesh) => gltf_mesh,
+            None => {
                 rfd::MessageDialog::new()
                     .set_level(MessageLevel::Error)
                     .set_title("Add gltf mesh error")
-                    .set_description("Primitive not loaded!Try a few seconds later.")
+                    .set_description("Gltf_mesh not loaded!\nTry a few seconds later.")
                     .set_buttons(MessageButtons::Ok)
                     .show();
-            };
-        } else {
+                continue;
+            },
+        };
+
+        if commands.get_entity(*entity).is_none() {
+            continue;
+        }
+
+        despawn_primitives(&mut commands, *entity, &children_query, &primitive_query);
+
+        if !spawn_primitives(&mut commands, *entity, gltf_mesh, *transform) {
             rfd::MessageDialog::new()
                 .set_level(MessageLevel::Error)
                 .set_title("Add gltf mesh error")
-                .set_description("Gltf_mesh not loaded!\nTry a few seconds later.")
+                .set_description("Primitive not loaded!Try a few seconds later.")
                 .set_buttons(MessageButtons::Ok)
                 .show();
+            continue;
+        }
+
+        let collider = if_none_continue!(collider);
+
+        log::info!("process_add_gltf_mesh collider");
+
+        if collider.collider_type == ColliderType::FromBevyMesh {
+            let primitive_meshes: Vec<(Transform, &Mesh)> = gltf_mesh.primitives
+                .iter()
+                .filter_map(|primitive| meshes.get(&primitive.mesh))
+                .map(|mesh| (Transform::IDENTITY, mesh))
+                .collect();
+
+            add_collider_from_meshes(&mut commands, entity, &primitive_meshes, &collider.collider_data);
+        } else {
+            collider_writer.send(CreateColliderEvent {
+                entity: *entity,
+                collider: collider.to_owned(),
+                transform: None,
+            });
+        }
+    }
+}
+
+// Spawns the node with its mesh and child nodes on the entity, collects the meshes with
+// their transforms relative to the object for the collider.
+pub(crate) fn spawn_node(
+    commands: &mut Commands,
+    entity: Entity,
+    node: &GltfNode,
+    transform: Transform,
+    offset: Transform,
+    gltf_meshes: &Assets<GltfMesh>,
+    parts: &mut Vec<(Transform, Handle<Mesh>)>,
+) {
+    let gltf_mesh = node.mesh.as_ref().and_then(|handle| gltf_meshes.get(handle));
+
+    let has_mesh = match gltf_mesh {
+        Some(gltf_mesh) => {
+            parts.extend(gltf_mesh.primitives.iter().map(|primitive| (offset, primitive.mesh.clone())));
+            spawn_primitives(commands, entity, gltf_mesh, transform)
+        },
+        None => false,
+    };
+
+    if !has_mesh {
+        commands.entity(entity).insert(SpatialBundle {
+            transform,
+            ..default()
+        });
+    }
+
+    for (index, child) in node.children.iter().enumerate() {
+        let child_entity = commands
+            .spawn((Name::new(format!("node {}", index)), MeshPrimitive))
+            .id();
+
+        commands.entity(entity).add_child(child_entity);
+
+        spawn_node(commands, child_entity, child, child.transform, offset * child.transform, gltf_meshes, parts);
+    }
+}
+
+pub fn process_add_gltf_node(
+    mut commands: Commands,
+    mut reader: EventReader<AddGltfNodeEvent>,
+    gltf_nodes: Res<Assets<GltfNode>>,
+    gltf_meshes: Res<Assets<GltfMesh>>,
+    meshes: Res<Assets<Mesh>>,
+    children_query: Query<&Children>,
+    primitive_query: Query<(), With<MeshPrimitive>>,
+    mut collider_writer: EventWriter<CreateColliderEvent>,
+) {
+    for AddGltfNodeEvent {
+        entity,
+        handle,
+        collider,
+        transform,
+    } in reader.iter()
+    {
+        log::info!("process_add_gltf_node");
+
+        let node = match gltf_nodes.get(handle) {
+            Some(node) => node,
+            None => {
+                rfd::MessageDialog::new()
+                    .set_level(MessageLevel::Error)
+                    .set_title("Add gltf node error")
+                    .set_description("Gltf_node not loaded!\nTry a few seconds later.")
+                    .set_buttons(MessageButtons::Ok)
+                    .show();
+                continue;
+            },
         };
+
+        if commands.get_entity(*entity).is_none() {
+            continue;
+        }
+
+        despawn_primitives(&mut commands, *entity, &children_query, &primitive_query);
+
+        let mut parts = Vec::new();
+
+        spawn_node(&mut commands, *entity, node, *transform, Transform::IDENTITY, &gltf_meshes, &mut parts);
+
+        let collider = if_none_continue!(collider);
+
+        if collider.collider_type == ColliderType::FromBevyMesh {
+            let part_meshes: Vec<(Transform, &Mesh)> = parts
+                .iter()
+                .filter_map(|(offset, handle)| meshes.get(handle).map(|mesh| (*offset, mesh)))
+                .collect();
+
+            add_collider_from_meshes(&mut commands, entity, &part_meshes, &collider.collider_data);
+        } else {
+            collider_writer.send(CreateColliderEvent {
+                entity: *entity,
+                collider: collider.to_owned(),
+                transform: None,
+            });
+        }
     }
 }
+
+#[derive(Default, Resource)]
+pub struct PendingGltfs {
+    handles: Vec<(PathBuf, Handle<Gltf>)>,
+}
+
+// Adds the scenes, meshes and nodes of a gltf file to the object palette once it is loaded.
+pub fn process_load_gltf(
+    mut reader: EventReader<ImportObjectEvent>,
+    mut pending: ResMut<PendingGltfs>,
+    mut editor_state: ResMut<MyEditorState>,
+    file_state: Res<FileState>,
+    asset_server: Res<AssetServer>,
+    gltf_assets: Res<Assets<Gltf>>,
+    mut error_writer: EventWriter<LoadErrorEvent>,
+) {
+    for ImportObjectEvent { importer, path } in reader.iter() {
+        if importer != GLTF_IMPORTER {
+            continue;
+        }
+
+        log::info!("process_load_gltf {}", path.display());
+
+        pending.handles.push((path.clone(), asset_server.load(file_state.to_load_path(path))));
+    }
+
+    let mut waiting = Vec::new();
+
+    for (path, handle) in pending.handles.drain(..) {
+        match asset_server.get_load_state(&handle) {
+            LoadState::Loaded => (),
+            LoadState::Failed => {
+                error_writer.send(LoadErrorEvent::new(Some(path), None, "failed to load the gltf file, see the log for details"));
+                continue;
+            },
+            _ => {
+                waiting.push((path, handle));
+                continue;
+            },
+        };
+
+        let gltf = if_none_continue!(gltf_assets.get(&handle));
+
+        let rel_path = path.display().to_string();
+
+        let entries = [
+            ("gltf_scene:", ObjectType::Scene, "Scene", handle_names(&gltf.scenes, &gltf.named_scenes)),
+            ("gltf_mesh:", ObjectType::Mesh, "Mesh", handle_names(&gltf.meshes, &gltf.named_meshes)),
+            ("gltf_node:", ObjectType::Node, "Node", handle_names(&gltf.nodes, &gltf.named_nodes)),
+        ];
+
+        for (prefix, object_type, label_prefix, names) in entries.into_iter() {
+            for (index, name) in names.into_iter().enumerate() {
+                let label = format!("{}{}", label_prefix, index);
+                let name = name.unwrap_or(label.clone());
+
+                editor_state.objects.insert(
+                    prefix.to_string() + &rel_path + "#" + &name,
+                    Object {
+                        object_type: object_type.clone(),
+                        path: Some(path.clone()),
+                        label: Some(label),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+
+    pending.handles = waiting;
+}
+
+// Names of the sub-assets in the order of their labels, `None` for unnamed ones.
+fn handle_names<T: bevy::asset::Asset>(handles: &[Handle<T>], named: &HashMap<String, Handle<T>>) -> Vec<Option<String>> {
+    let names: HashMap<&Handle<T>, &String> = named.iter().map(|(name, handle)| (handle, name)).collect();
+
+    handles
+        .iter()
+        .map(|handle| names.get(handle).map(|name| name.to_string()))
+        .collect()
+}
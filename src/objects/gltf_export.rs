@@ -0,0 +1,659 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use bevy::{log, prelude::*, utils::HashMap};
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy_mod_picking::highlight::InitialHighlight;
+use bevy_mod_picking::prelude::Highlight;
+use serde_json::{json, Value};
+
+use crate::if_none_return;
+use super::{Object, EditorId, Collider, ColliderType, FROM_FLOAT};
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+const COMPONENT_U16: u32 = 5123;
+const COMPONENT_U32: u32 = 5125;
+const COMPONENT_F32: u32 = 5126;
+
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+// `.glb` writes a binary file, anything else a `.gltf` with the buffer embedded as a data uri.
+pub struct ExportGltfEvent {
+    pub path: PathBuf,
+}
+
+pub(crate) fn process_export_gltf(
+    world: &World,
+    mut reader: EventReader<ExportGltfEvent>,
+    root_query: Query<(Entity, &EditorId), (With<Object>, Without<Parent>)>,
+) {
+    let ExportGltfEvent { path } = if_none_return!(reader.iter().last());
+
+    log::info!("process_export_gltf {:?}", path);
+
+    let mut roots: Vec<(Entity, &EditorId)> = root_query.iter().collect();
+    roots.sort_by(|a, b| a.1.cmp(b.1));
+
+    let mut exporter = GltfExporter::new(world);
+
+    let nodes: Vec<usize> = roots
+        .into_iter()
+        .map(|(entity, _)| exporter.export_node(entity))
+        .collect();
+
+    let result = if path.extension().map_or(false, |extension| extension.eq_ignore_ascii_case("glb")) {
+        exporter.write_glb(path, nodes)
+    } else {
+        exporter.write_gltf(path, nodes)
+    };
+
+    if let Err(e) = result {
+        log::error!("process_export_gltf: {}", e);
+
+        rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Error)
+            .set_title("Export glTF error")
+            .set_description(&format!("Failed to export {}: {}", path.display(), e))
+            .set_buttons(rfd::MessageButtons::Ok)
+            .show();
+    }
+}
+
+// Walks the spawned level, so nested ron objects and loaded scenes are exported already
+// flattened into plain nodes.
+struct GltfExporter<'w> {
+    world: &'w World,
+    buffer: Vec<u8>,
+    nodes: Vec<Value>,
+    meshes: Vec<Value>,
+    materials: Vec<Value>,
+    textures: Vec<Value>,
+    images: Vec<Value>,
+    accessors: Vec<Value>,
+    buffer_views: Vec<Value>,
+    mesh_indices: HashMap<(Handle<Mesh>, Option<Handle<StandardMaterial>>), Option<usize>>,
+    material_indices: HashMap<Handle<StandardMaterial>, usize>,
+    texture_indices: HashMap<Handle<Image>, Option<usize>>,
+}
+
+impl<'w> GltfExporter<'w> {
+    fn new(world: &'w World) -> Self {
+        Self {
+            world,
+            buffer: Vec::new(),
+            nodes: Vec::new(),
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            textures: Vec::new(),
+            images: Vec::new(),
+            accessors: Vec::new(),
+            buffer_views: Vec::new(),
+            mesh_indices: HashMap::new(),
+            material_indices: HashMap::new(),
+            texture_indices: HashMap::new(),
+        }
+    }
+
+    fn export_node(&mut self, entity: Entity) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(json!({}));
+
+        let transform = self.world.get::<Transform>(entity).copied().unwrap_or_default();
+
+        let mut node = json!({
+            "translation": transform.translation.to_array(),
+            "rotation": transform.rotation.to_array(),
+            "scale": transform.scale.to_array(),
+        });
+
+        if let Some(name) = self.world.get::<Name>(entity) {
+            node["name"] = json!(name.as_str());
+        }
+
+        // Scene objects carry a bounding box for picking, it is not a part of the level.
+        if self.world.get::<Highlight<StandardMaterial>>(entity).is_none() {
+            if let Some(mesh) = self.world.get::<Handle<Mesh>>(entity) {
+                let material = match self.world.get::<InitialHighlight<StandardMaterial>>(entity) {
+                    Some(initial) => Some(initial.initial.clone()),
+                    None => self.world.get::<Handle<StandardMaterial>>(entity).cloned(),
+                };
+
+                if let Some(mesh) = self.export_mesh(mesh, material) {
+                    node["mesh"] = json!(mesh);
+                }
+            }
+        }
+
+        if let Some(object) = self.world.get::<Object>(entity) {
+            node["extras"] = object_extras(object, self.world.get::<EditorId>(entity));
+        }
+
+        if let Some(children) = self.world.get::<Children>(entity) {
+            let children: Vec<usize> = children
+                .iter()
+                .map(|child| self.export_node(*child))
+                .collect();
+
+            if !children.is_empty() {
+                node["children"] = json!(children);
+            }
+        }
+
+        self.nodes[index] = node;
+
+        index
+    }
+
+    fn export_mesh(&mut self, handle: &Handle<Mesh>, material: Option<Handle<StandardMaterial>>) -> Option<usize> {
+        let key = (handle.clone_weak(), material.as_ref().map(|material| material.clone_weak()));
+
+        if let Some(index) = self.mesh_indices.get(&key) {
+            return *index;
+        }
+
+        let index = self.create_mesh(handle, material);
+        self.mesh_indices.insert(key, index);
+
+        index
+    }
+
+    fn create_mesh(&mut self, handle: &Handle<Mesh>, material: Option<Handle<StandardMaterial>>) -> Option<usize> {
+        let world = self.world;
+        let meshes = world.resource::<Assets<Mesh>>();
+        let mesh = meshes.get(handle)?;
+
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            log::warn!("export_mesh: skip mesh with {:?} topology", mesh.primitive_topology());
+            return None;
+        }
+
+        let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) if !positions.is_empty() => positions,
+            _ => return None,
+        };
+
+        let mut min = Vec3::splat(f32::MAX);
+        let mut max = Vec3::splat(f32::MIN);
+
+        for position in positions.iter() {
+            min = min.min(Vec3::from(*position));
+            max = max.max(Vec3::from(*position));
+        }
+
+        let mut attributes = json!({});
+
+        let accessor = self.push_accessor(f32_bytes(positions.iter().flatten()), positions.len(), "VEC3", COMPONENT_F32, TARGET_ARRAY_BUFFER);
+        self.accessors[accessor]["min"] = json!(min.to_array());
+        self.accessors[accessor]["max"] = json!(max.to_array());
+        attributes["POSITION"] = json!(accessor);
+
+        if let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            attributes["NORMAL"] = json!(self.push_accessor(f32_bytes(normals.iter().flatten()), normals.len(), "VEC3", COMPONENT_F32, TARGET_ARRAY_BUFFER));
+        }
+
+        if let Some(VertexAttributeValues::Float32x4(tangents)) = mesh.attribute(Mesh::ATTRIBUTE_TANGENT) {
+            attributes["TANGENT"] = json!(self.push_accessor(f32_bytes(tangents.iter().flatten()), tangents.len(), "VEC4", COMPONENT_F32, TARGET_ARRAY_BUFFER));
+        }
+
+        if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            attributes["TEXCOORD_0"] = json!(self.push_accessor(f32_bytes(uvs.iter().flatten()), uvs.len(), "VEC2", COMPONENT_F32, TARGET_ARRAY_BUFFER));
+        }
+
+        let mut primitive = json!({ "attributes": attributes });
+
+        match mesh.indices() {
+            Some(Indices::U16(indices)) => {
+                let bytes = indices.iter().flat_map(|index| index.to_le_bytes()).collect();
+                primitive["indices"] = json!(self.push_accessor(bytes, indices.len(), "SCALAR", COMPONENT_U16, TARGET_ELEMENT_ARRAY_BUFFER));
+            },
+            Some(Indices::U32(indices)) => {
+                let bytes = indices.iter().flat_map(|index| index.to_le_bytes()).collect();
+                primitive["indices"] = json!(self.push_accessor(bytes, indices.len(), "SCALAR", COMPONENT_U32, TARGET_ELEMENT_ARRAY_BUFFER));
+            },
+            None => (),
+        };
+
+        if let Some(material) = material {
+            if let Some(material) = self.export_material(&material) {
+                primitive["material"] = json!(material);
+            }
+        }
+
+        self.meshes.push(json!({ "primitives": [primitive] }));
+
+        Some(self.meshes.len() - 1)
+    }
+
+    fn export_material(&mut self, handle: &Handle<StandardMaterial>) -> Option<usize> {
+        if let Some(index) = self.material_indices.get(handle) {
+            return Some(*index);
+        }
+
+        let materials = self.world.resource::<Assets<StandardMaterial>>();
+        let material = materials.get(handle)?.clone();
+
+        let mut pbr = json!({
+            "baseColorFactor": material.base_color.as_linear_rgba_f32(),
+            "metallicFactor": material.metallic,
+            "roughnessFactor": material.perceptual_roughness,
+        });
+
+        if let Some(texture) = self.export_texture(&material.base_color_texture) {
+            pbr["baseColorTexture"] = json!({ "index": texture });
+        }
+
+        if let Some(texture) = self.export_texture(&material.metallic_roughness_texture) {
+            pbr["metallicRoughnessTexture"] = json!({ "index": texture });
+        }
+
+        let emissive = material.emissive.as_linear_rgba_f32();
+
+        let mut value = json!({
+            "pbrMetallicRoughness": pbr,
+            "emissiveFactor": [emissive[0], emissive[1], emissive[2]],
+            "doubleSided": material.double_sided,
+        });
+
+        match material.alpha_mode {
+            AlphaMode::Opaque => value["alphaMode"] = json!("OPAQUE"),
+            AlphaMode::Mask(cutoff) => {
+                value["alphaMode"] = json!("MASK");
+                value["alphaCutoff"] = json!(cutoff);
+            },
+            _ => value["alphaMode"] = json!("BLEND"),
+        };
+
+        if let Some(texture) = self.export_texture(&material.normal_map_texture) {
+            value["normalTexture"] = json!({ "index": texture });
+        }
+
+        if let Some(texture) = self.export_texture(&material.occlusion_texture) {
+            value["occlusionTexture"] = json!({ "index": texture });
+        }
+
+        if let Some(texture) = self.export_texture(&material.emissive_texture) {
+            value["emissiveTexture"] = json!({ "index": texture });
+        }
+
+        self.materials.push(value);
+
+        let index = self.materials.len() - 1;
+        self.material_indices.insert(handle.clone_weak(), index);
+
+        Some(index)
+    }
+
+    // Textures are re-encoded to png, whatever format they were loaded from.
+    fn export_texture(&mut self, handle: &Option<Handle<Image>>) -> Option<usize> {
+        let handle = handle.as_ref()?;
+
+        if let Some(index) = self.texture_indices.get(handle) {
+            return *index;
+        }
+
+        let index = self.create_texture(handle);
+        self.texture_indices.insert(handle.clone_weak(), index);
+
+        index
+    }
+
+    fn create_texture(&mut self, handle: &Handle<Image>) -> Option<usize> {
+        let images = self.world.resource::<Assets<Image>>();
+        let image = images.get(handle)?.clone();
+
+        let image = match image.try_into_dynamic() {
+            Ok(image) => image,
+            Err(e) => {
+                log::warn!("export_texture: skip texture: {}", e);
+                return None;
+            }
+        };
+
+        let mut bytes = Vec::new();
+
+        if let Err(e) = image.write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png) {
+            log::warn!("export_texture: skip texture: {}", e);
+            return None;
+        }
+
+        let view = self.push_buffer_view(bytes, None);
+
+        self.images.push(json!({ "bufferView": view, "mimeType": "image/png" }));
+        self.textures.push(json!({ "source": self.images.len() - 1 }));
+
+        Some(self.textures.len() - 1)
+    }
+
+    fn push_accessor(&mut self, bytes: Vec<u8>, count: usize, accessor_type: &str, component_type: u32, target: u32) -> usize {
+        let view = self.push_buffer_view(bytes, Some(target));
+
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": component_type,
+            "count": count,
+            "type": accessor_type,
+        }));
+
+        self.accessors.len() - 1
+    }
+
+    fn push_buffer_view(&mut self, bytes: Vec<u8>, target: Option<u32>) -> usize {
+        while self.buffer.len() % 4 != 0 {
+            self.buffer.push(0);
+        }
+
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.buffer.len(),
+            "byteLength": bytes.len(),
+        });
+
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+
+        self.buffer.extend(bytes);
+        self.buffer_views.push(view);
+
+        self.buffer_views.len() - 1
+    }
+
+    fn document(&mut self, roots: Vec<usize>, uri: Option<String>) -> Value {
+        while self.buffer.len() % 4 != 0 {
+            self.buffer.push(0);
+        }
+
+        let mut buffer = json!({ "byteLength": self.buffer.len() });
+
+        if let Some(uri) = uri {
+            buffer["uri"] = json!(uri);
+        }
+
+        let mut document = json!({
+            "asset": { "version": "2.0", "generator": "kns_bevy_editor3d" },
+            "scene": 0,
+            "scenes": [{ "nodes": roots }],
+            "nodes": self.nodes,
+        });
+
+        for (name, values) in [
+            ("meshes", &self.meshes),
+            ("materials", &self.materials),
+            ("textures", &self.textures),
+            ("images", &self.images),
+            ("accessors", &self.accessors),
+            ("bufferViews", &self.buffer_views),
+        ] {
+            if !values.is_empty() {
+                document[name] = json!(values);
+            }
+        }
+
+        if !self.buffer.is_empty() {
+            document["buffers"] = json!([buffer]);
+        }
+
+        document
+    }
+
+    fn write_glb(self, path: &Path, roots: Vec<usize>) -> Result<(), String> {
+        let glb = self.to_glb(roots)?;
+
+        std::fs::write(path, glb).map_err(|e| e.to_string())
+    }
+
+    fn to_glb(mut self, roots: Vec<usize>) -> Result<Vec<u8>, String> {
+        let mut json = serde_json::to_vec(&self.document(roots, None)).map_err(|e| e.to_string())?;
+
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+
+        let mut length = 12 + 8 + json.len();
+
+        if !self.buffer.is_empty() {
+            length += 8 + self.buffer.len();
+        }
+
+        let mut glb = Vec::with_capacity(length);
+
+        glb.extend(GLB_MAGIC.to_le_bytes());
+        glb.extend(2u32.to_le_bytes());
+        glb.extend((length as u32).to_le_bytes());
+
+        glb.extend((json.len() as u32).to_le_bytes());
+        glb.extend(GLB_CHUNK_JSON.to_le_bytes());
+        glb.extend(json);
+
+        if !self.buffer.is_empty() {
+            glb.extend((self.buffer.len() as u32).to_le_bytes());
+            glb.extend(GLB_CHUNK_BIN.to_le_bytes());
+            glb.extend(&self.buffer);
+        }
+
+        Ok(glb)
+    }
+
+    fn write_gltf(mut self, path: &Path, roots: Vec<usize>) -> Result<(), String> {
+        use base64::Engine;
+
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&self.buffer),
+        );
+
+        let json = serde_json::to_string_pretty(&self.document(roots, Some(uri))).map_err(|e| e.to_string())?;
+
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+fn f32_bytes<'a>(values: impl Iterator<Item = &'a f32>) -> Vec<u8> {
+    values.flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn object_extras(object: &Object, id: Option<&EditorId>) -> Value {
+    let mut extras = json!({ "object_type": format!("{:?}", object.object_type) });
+
+    if let Some(id) = id {
+        extras["editor_id"] = json!(id.0.to_string());
+    }
+
+    if let Some(path) = &object.path {
+        extras["path"] = json!(path.display().to_string());
+    }
+
+    if let Some(collider) = &object.collider {
+        extras["collider"] = collider_extras(collider);
+    }
+
+    extras
+}
+
+fn collider_extras(collider: &Collider) -> Value {
+    let to_float = |value: u32| value as f32 * FROM_FLOAT;
+
+    let data = &collider.collider_data;
+
+    let mut extras = json!({
+        "type": collider.collider_type.to_string(),
+        "friction": data.get_friction(),
+        "fixed": data.fixed,
+        "collision_groups": [data.collision_group_self, data.collision_group_filter],
+        "solver_groups": [data.solver_group_self, data.solver_group_filter],
+    });
+
+    match collider.collider_type {
+        ColliderType::FromBevyMesh => (),
+        ColliderType::Ball(radius) => extras["radius"] = json!(to_float(radius)),
+        ColliderType::Cuboid((hx, hy, hz)) => extras["half_extents"] = json!([to_float(hx), to_float(hy), to_float(hz)]),
+        ColliderType::Cylinder((half_height, radius)) | ColliderType::Cone((half_height, radius)) => {
+            extras["half_height"] = json!(to_float(half_height));
+            extras["radius"] = json!(to_float(radius));
+        },
+    };
+
+    extras
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::AssetPlugin;
+    use crate::objects::{ObjectType, ColliderData, TO_FLOAT};
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    // A root collider with a triangle, the 6 bytes of its indices need padding, and a cube child.
+    fn level() -> (App, Entity) {
+        let mut app = App::new();
+
+        app
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_asset::<Image>();
+
+        let mut triangle = Mesh::new(PrimitiveTopology::TriangleList);
+        triangle.insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0., 0., 0.], [1., 0., 0.], [0., 1., 0.]]);
+        triangle.set_indices(Some(Indices::U16(vec![0, 1, 2])));
+
+        let mut meshes = app.world.resource_mut::<Assets<Mesh>>();
+        let triangle = meshes.add(triangle);
+        let cube = meshes.add(shape::Cube::new(1.).into());
+
+        let half = (0.5 * TO_FLOAT) as u32;
+
+        let object = Object {
+            object_type: ObjectType::Collider,
+            collider: Some(Collider {
+                collider_type: ColliderType::Cuboid((half, half, half)),
+                collider_data: ColliderData::new(0.5, true, 1, 2, 3, 4),
+            }),
+            ..default()
+        };
+
+        let child = app.world.spawn((Transform::default(), cube)).id();
+
+        let root = app.world
+            .spawn((Transform::from_xyz(1., 2., 3.), Name::new("root"), object, EditorId::default(), triangle))
+            .add_child(child)
+            .id();
+
+        (app, root)
+    }
+
+    fn export(app: &App, root: Entity) -> Vec<u8> {
+        let mut exporter = GltfExporter::new(&app.world);
+        let node = exporter.export_node(root);
+
+        exporter.to_glb(vec![node]).unwrap()
+    }
+
+    #[test]
+    fn glb_header_and_chunks() {
+        let (app, root) = level();
+        let glb = export(&app, root);
+
+        assert_eq!(u32_at(&glb, 0), GLB_MAGIC);
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(u32_at(&glb, 4), 2);
+        assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+
+        let json_length = u32_at(&glb, 12) as usize;
+        assert_eq!(u32_at(&glb, 16), GLB_CHUNK_JSON);
+        assert_eq!(json_length % 4, 0);
+
+        let bin = 20 + json_length;
+        let bin_length = u32_at(&glb, bin) as usize;
+        assert_eq!(u32_at(&glb, bin + 4), GLB_CHUNK_BIN);
+        assert_eq!(bin_length % 4, 0);
+        assert_eq!(bin + 8 + bin_length, glb.len());
+
+        let document: Value = serde_json::from_slice(&glb[20..bin]).unwrap();
+        assert_eq!(document["buffers"][0]["byteLength"], json!(bin_length));
+    }
+
+    #[test]
+    fn buffer_views_match_accessors() {
+        let (app, root) = level();
+        let glb = export(&app, root);
+
+        let json_length = u32_at(&glb, 12) as usize;
+        let bin_length = u32_at(&glb, 20 + json_length) as usize;
+        let document: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+
+        let accessors = document["accessors"].as_array().unwrap();
+        let views = document["bufferViews"].as_array().unwrap();
+
+        // Triangle positions and indices, cube positions, normals, uvs and indices.
+        assert_eq!(accessors.len(), 6);
+        assert_eq!(views.len(), 6);
+
+        let mut end = 0;
+
+        for accessor in accessors.iter() {
+            let view = &views[accessor["bufferView"].as_u64().unwrap() as usize];
+            let offset = view["byteOffset"].as_u64().unwrap() as usize;
+            let length = view["byteLength"].as_u64().unwrap() as usize;
+
+            let components = match accessor["type"].as_str().unwrap() {
+                "SCALAR" => 1,
+                "VEC2" => 2,
+                "VEC3" => 3,
+                "VEC4" => 4,
+                other => panic!("unexpected accessor type {}", other),
+            };
+
+            let size = match accessor["componentType"].as_u64().unwrap() as u32 {
+                COMPONENT_U16 => 2,
+                COMPONENT_U32 | COMPONENT_F32 => 4,
+                other => panic!("unexpected component type {}", other),
+            };
+
+            assert_eq!(offset % 4, 0);
+            assert!(offset >= end);
+            assert_eq!(length, accessor["count"].as_u64().unwrap() as usize * components * size);
+
+            end = offset + length;
+        }
+
+        assert!(end <= bin_length);
+
+        // The 6 bytes of the triangle indices are padded before the cube positions.
+        assert_eq!(views[1]["byteLength"], json!(6));
+        assert_eq!(views[2]["byteOffset"], json!(36 + 8));
+    }
+
+    #[test]
+    fn collider_is_in_node_extras() {
+        let (app, root) = level();
+        let glb = export(&app, root);
+
+        let json_length = u32_at(&glb, 12) as usize;
+        let document: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+
+        let node = &document["nodes"][0];
+        assert_eq!(node["name"], json!("root"));
+        assert_eq!(node["translation"], json!([1., 2., 3.]));
+        assert_eq!(node["children"], json!([1]));
+
+        let extras = &node["extras"];
+        assert_eq!(extras["object_type"], json!("Collider"));
+        assert_eq!(extras["editor_id"], json!(app.world.get::<EditorId>(root).unwrap().0.to_string()));
+
+        let collider = &extras["collider"];
+        assert_eq!(collider["type"], json!("ColliderCuboid"));
+        assert_eq!(collider["half_extents"], json!([0.5, 0.5, 0.5]));
+        assert_eq!(collider["fixed"], json!(true));
+        assert_eq!(collider["collision_groups"], json!([1, 2]));
+        assert_eq!(collider["solver_groups"], json!([3, 4]));
+
+        assert!(document["nodes"][1].get("extras").is_none());
+    }
+}
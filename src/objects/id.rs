@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, Uuid};
+use ::serde::{Serialize, Deserialize};
+
+// Persistent identity of an editor object, kept through save/load cycles.
+// Other objects refer to it by id, the live entity is found through `EditorIdMap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Component, Reflect, FromReflect, Serialize, Deserialize)]
+#[reflect_value(Component, Serialize, Deserialize, PartialEq, Hash)]
+pub struct EditorId(pub Uuid);
+
+impl Default for EditorId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl EditorId {
+    // Stable id for a node spawned from a nested ron, unique per instance.
+    pub fn combine(&self, other: &EditorId) -> EditorId {
+        let a = self.0.as_u128();
+        let b = other.0.as_u128();
+
+        EditorId(Uuid::from_u128(a.rotate_left(64) ^ b ^ 0x9e3779b97f4a7c15f39cc0605cedc834))
+    }
+}
+
+#[derive(Default, Debug, Resource)]
+pub struct EditorIdMap {
+    entities: HashMap<EditorId, Entity>,
+}
+
+impl EditorIdMap {
+    pub fn get(&self, id: &EditorId) -> Option<Entity> {
+        self.entities.get(id).copied()
+    }
+}
+
+pub(crate) fn process_editor_ids(
+    mut id_map: ResMut<EditorIdMap>,
+    id_query: Query<(Entity, &EditorId), Changed<EditorId>>,
+    mut removed: RemovedComponents<EditorId>,
+) {
+    for entity in removed.iter() {
+        id_map.entities.retain(|_, value| *value != entity);
+    }
+
+    for (entity, id) in id_query.iter() {
+        id_map.entities.insert(*id, entity);
+    }
+}
@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+use bevy::{log, prelude::*};
+
+use crate::gui::MyEditorState;
+use super::{Object, ObjectType};
+
+pub const GLTF_IMPORTER: &str = "glTF";
+
+// How the asset server loads the objects of an importer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportedAsset {
+    // Scenes, meshes and nodes of a gltf file by their label, the first scene or mesh
+    // for objects without a label.
+    Gltf,
+    // The whole file as one bevy `Scene`, by the asset loader of the format. The file is
+    // added to the palette as a scene object without a label.
+    Scene,
+}
+
+// A file format that adds objects to the palette, `extensions` are the file dialog filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectImporter {
+    pub name: String,
+    pub extensions: Vec<String>,
+    pub asset: ImportedAsset,
+}
+
+impl ObjectImporter {
+    pub fn new(name: &str, extensions: &[&str], asset: ImportedAsset) -> Self {
+        Self {
+            name: name.to_string(),
+            extensions: extensions.iter().map(|extension| extension.to_string()).collect(),
+            asset,
+        }
+    }
+
+    pub fn accepts(&self, path: &Path) -> bool {
+        path.extension().map_or(false, |extension| {
+            self.extensions.iter().any(|accepted| extension.eq_ignore_ascii_case(accepted.as_str()))
+        })
+    }
+}
+
+// Further formats with a bevy asset loader for `Scene` register an `ImportedAsset::Scene`
+// importer. Other formats handle `ImportObjectEvent` with the name of their importer,
+// usually by adding the objects of the file to `MyEditorState::objects`.
+#[derive(Resource, Debug)]
+pub struct ObjectImporters {
+    pub importers: Vec<ObjectImporter>,
+}
+
+impl Default for ObjectImporters {
+    fn default() -> Self {
+        Self {
+            importers: vec![ObjectImporter::new(GLTF_IMPORTER, &["gltf", "glb"], ImportedAsset::Gltf)],
+        }
+    }
+}
+
+impl ObjectImporters {
+    // An importer with the same name is replaced.
+    pub fn register(&mut self, importer: ObjectImporter) -> &mut Self {
+        self.importers.retain(|registered| registered.name != importer.name);
+        self.importers.push(importer);
+        self
+    }
+
+    pub fn find(&self, path: &Path) -> Option<&ObjectImporter> {
+        self.importers.iter().find(|importer| importer.accepts(path))
+    }
+
+    // The sub-asset of the object to load, `None` for objects loaded from the whole file.
+    // Files without an importer are taken as gltf files.
+    pub fn asset_label(&self, object: &Object) -> Option<String> {
+        let asset = object.path
+            .as_ref()
+            .and_then(|path| self.find(path))
+            .map_or(ImportedAsset::Gltf, |importer| importer.asset);
+
+        match asset {
+            ImportedAsset::Gltf => object.gltf_label(),
+            ImportedAsset::Scene => None,
+        }
+    }
+
+    pub fn extensions(&self) -> Vec<&str> {
+        self.importers
+            .iter()
+            .flat_map(|importer| importer.extensions.iter().map(String::as_str))
+            .collect()
+    }
+}
+
+// A picked file for the importer of the `importer` name, the path is an asset path.
+pub struct ImportObjectEvent {
+    pub importer: String,
+    pub path: PathBuf,
+}
+
+// Files of `ImportedAsset::Scene` importers are one object.
+pub(crate) fn process_import_scene_files(
+    mut reader: EventReader<ImportObjectEvent>,
+    importers: Res<ObjectImporters>,
+    mut editor_state: ResMut<MyEditorState>,
+) {
+    for ImportObjectEvent { importer, path } in reader.iter() {
+        let scene_importer = importers.importers
+            .iter()
+            .any(|registered| registered.name == *importer && registered.asset == ImportedAsset::Scene);
+
+        if !scene_importer {
+            continue;
+        }
+
+        log::info!("process_import_scene_files {}", path.display());
+
+        editor_state.objects.insert(
+            importer.to_lowercase() + ":" + &path.display().to_string(),
+            Object {
+                object_type: ObjectType::Scene,
+                path: Some(path.clone()),
+                ..Default::default()
+            },
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use bevy::pbr::{CascadeShadowConfig, Cascades, CascadesVisibleEntities, CubemapVisibleEntities, NotShadowCaster};
+use bevy::render::primitives::{CascadesFrusta, CubemapFrusta, Frustum};
+use bevy::render::view::VisibleEntities;
+use bevy::{log, prelude::*};
+use ::serde::{Deserialize, Serialize};
+
+use super::collider::{FROM_FLOAT, TO_FLOAT};
+use super::spawn::{Resources, HIGHLIGHT_TINT};
+use super::{Object, ObjectType};
+
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum LightType {
+    #[default]
+    Point,
+    Spot,
+    Directional,
+}
+
+impl ToString for LightType {
+    fn to_string(&self) -> String {
+        return match self {
+            LightType::Point => "point light".to_string(),
+            LightType::Spot => "spot light".to_string(),
+            LightType::Directional => "directional light".to_string(),
+        };
+    }
+}
+
+// Fixed point values like `ColliderData`, so that objects stay hashable. The setters round,
+// so a value read and written back is unchanged.
+// `intensity` is in lumens for point and spot lights and in lux for directional ones.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct LightData {
+    pub light_type: LightType,
+    pub color: [u32; 4],
+    pub intensity: u32,
+    pub range: u32,
+    pub radius: u32,
+    pub inner_angle: u32,
+    pub outer_angle: u32,
+    pub shadows_enabled: bool,
+    pub shadow_depth_bias: u32,
+    pub shadow_normal_bias: u32,
+}
+
+impl LightData {
+    // Bevy defaults of the light type.
+    pub fn new(light_type: LightType) -> Self {
+        let point = PointLight::default();
+        let spot = SpotLight::default();
+        let directional = DirectionalLight::default();
+
+        let mut data = Self {
+            light_type,
+            ..Default::default()
+        };
+
+        data.set_range(point.range);
+        data.set_radius(point.radius);
+        data.set_inner_angle(spot.inner_angle);
+        data.set_outer_angle(spot.outer_angle);
+
+        match light_type {
+            LightType::Point => {
+                data.set_color(point.color);
+                data.set_intensity(point.intensity);
+                data.set_shadow_depth_bias(point.shadow_depth_bias);
+                data.set_shadow_normal_bias(point.shadow_normal_bias);
+            },
+            LightType::Spot => {
+                data.set_color(spot.color);
+                data.set_intensity(spot.intensity);
+                data.set_shadow_depth_bias(spot.shadow_depth_bias);
+                data.set_shadow_normal_bias(spot.shadow_normal_bias);
+            },
+            LightType::Directional => {
+                data.set_color(directional.color);
+                data.set_intensity(directional.illuminance);
+                data.set_shadow_depth_bias(directional.shadow_depth_bias);
+                data.set_shadow_normal_bias(directional.shadow_normal_bias);
+            },
+        };
+
+        data
+    }
+
+    pub fn get_color(&self) -> Color {
+        Color::rgba(
+            self.color[0] as f32 * FROM_FLOAT,
+            self.color[1] as f32 * FROM_FLOAT,
+            self.color[2] as f32 * FROM_FLOAT,
+            self.color[3] as f32 * FROM_FLOAT,
+        )
+    }
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color.as_rgba_f32().map(|value| (value * TO_FLOAT).round() as u32);
+    }
+
+    pub fn get_intensity(&self) -> f32 { self.intensity as f32 * FROM_FLOAT }
+    pub fn set_intensity(&mut self, value: f32) { self.intensity = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_range(&self) -> f32 { self.range as f32 * FROM_FLOAT }
+    pub fn set_range(&mut self, value: f32) { self.range = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_radius(&self) -> f32 { self.radius as f32 * FROM_FLOAT }
+    pub fn set_radius(&mut self, value: f32) { self.radius = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_inner_angle(&self) -> f32 { self.inner_angle as f32 * FROM_FLOAT }
+    pub fn set_inner_angle(&mut self, value: f32) { self.inner_angle = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_outer_angle(&self) -> f32 { self.outer_angle as f32 * FROM_FLOAT }
+    pub fn set_outer_angle(&mut self, value: f32) { self.outer_angle = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_shadow_depth_bias(&self) -> f32 { self.shadow_depth_bias as f32 * FROM_FLOAT }
+    pub fn set_shadow_depth_bias(&mut self, value: f32) { self.shadow_depth_bias = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_shadow_normal_bias(&self) -> f32 { self.shadow_normal_bias as f32 * FROM_FLOAT }
+    pub fn set_shadow_normal_bias(&mut self, value: f32) { self.shadow_normal_bias = (value * TO_FLOAT).round() as u32; }
+
+    pub fn to_point_light(&self) -> PointLight {
+        PointLight {
+            color: self.get_color(),
+            intensity: self.get_intensity(),
+            range: self.get_range(),
+            radius: self.get_radius(),
+            shadows_enabled: self.shadows_enabled,
+            shadow_depth_bias: self.get_shadow_depth_bias(),
+            shadow_normal_bias: self.get_shadow_normal_bias(),
+        }
+    }
+
+    pub fn to_spot_light(&self) -> SpotLight {
+        SpotLight {
+            color: self.get_color(),
+            intensity: self.get_intensity(),
+            range: self.get_range(),
+            radius: self.get_radius(),
+            shadows_enabled: self.shadows_enabled,
+            shadow_depth_bias: self.get_shadow_depth_bias(),
+            shadow_normal_bias: self.get_shadow_normal_bias(),
+            // Bevy requires the inner angle to be inside the cone.
+            inner_angle: self.get_inner_angle().min(self.get_outer_angle()),
+            outer_angle: self.get_outer_angle(),
+        }
+    }
+
+    pub fn to_directional_light(&self) -> DirectionalLight {
+        DirectionalLight {
+            color: self.get_color(),
+            illuminance: self.get_intensity(),
+            shadows_enabled: self.shadows_enabled,
+            shadow_depth_bias: self.get_shadow_depth_bias(),
+            shadow_normal_bias: self.get_shadow_normal_bias(),
+        }
+    }
+}
+
+// The light components without transform and visibility, the light type can change
+// on an existing entity.
+pub fn insert_light_components(entity_commands: &mut bevy::ecs::system::EntityCommands, light: &LightData) {
+    match light.light_type {
+        LightType::Point => {
+            entity_commands
+                .remove::<(SpotLight, VisibleEntities, Frustum)>()
+                .remove::<(DirectionalLight, CascadesFrusta, Cascades, CascadeShadowConfig, CascadesVisibleEntities)>()
+                .insert((light.to_point_light(), CubemapVisibleEntities::default(), CubemapFrusta::default()));
+        },
+        LightType::Spot => {
+            entity_commands
+                .remove::<(PointLight, CubemapVisibleEntities, CubemapFrusta)>()
+                .remove::<(DirectionalLight, CascadesFrusta, Cascades, CascadeShadowConfig, CascadesVisibleEntities)>()
+                .insert((light.to_spot_light(), VisibleEntities::default(), Frustum::default()));
+        },
+        LightType::Directional => {
+            entity_commands
+                .remove::<(PointLight, CubemapVisibleEntities, CubemapFrusta)>()
+                .remove::<(SpotLight, VisibleEntities, Frustum)>()
+                .insert((
+                    light.to_directional_light(),
+                    CascadesFrusta::default(),
+                    Cascades::default(),
+                    CascadeShadowConfig::default(),
+                    CascadesVisibleEntities::default(),
+                ));
+        },
+    };
+}
+
+// Lights have no mesh, a small sphere shows and picks them in the viewport.
+// It is skipped by the glTF export like the bounding boxes of scenes.
+pub(crate) fn insert_light_icon(
+    entity_commands: &mut bevy::ecs::system::EntityCommands,
+    transform: Transform,
+    resources: &Resources,
+) {
+    entity_commands.insert((
+        PbrBundle {
+            mesh: resources.light_icon_mesh.clone().unwrap_or_default(),
+            material: resources.light_icon_material.clone().unwrap_or_default(),
+            transform,
+            ..default()
+        },
+        HIGHLIGHT_TINT.clone(),
+        NotShadowCaster,
+    ));
+}
+
+// Light objects follow their `Object`, which is changed by the right panel.
+pub(crate) fn process_lights(
+    mut commands: Commands,
+    object_query: Query<(Entity, &Object), Changed<Object>>,
+) {
+    for (entity, object) in object_query.iter() {
+        if object.object_type != ObjectType::Light {
+            continue;
+        }
+
+        let light = crate::if_none_continue!(object.light.as_ref());
+
+        log::info!("process_lights {:?}", light.light_type);
+
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            insert_light_components(&mut entity_commands, light);
+        }
+    }
+}
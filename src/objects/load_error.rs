@@ -0,0 +1,75 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use bevy::{log, prelude::*};
+
+use super::EditorId;
+
+// A problem found while loading a level, the rest of the level is still loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadErrorEvent {
+    pub file: Option<PathBuf>,
+    pub node: Option<EditorId>,
+    pub reason: String,
+}
+
+impl LoadErrorEvent {
+    pub fn new(file: Option<PathBuf>, node: Option<EditorId>, reason: impl Into<String>) -> Self {
+        Self {
+            file,
+            node,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for LoadErrorEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+
+        if let Some(node) = &self.node {
+            write!(f, "node {}: ", node.0)?;
+        }
+
+        write!(f, "{}", self.reason)
+    }
+}
+
+// Errors of the asset loaders, they run on other threads and can't send events.
+#[derive(Resource, Clone, Default)]
+pub struct LoadErrorQueue(Arc<Mutex<Vec<LoadErrorEvent>>>);
+
+impl LoadErrorQueue {
+    pub fn push(&self, error: LoadErrorEvent) {
+        if let Ok(mut errors) = self.0.lock() {
+            errors.push(error);
+        }
+    }
+
+    fn drain(&self) -> Vec<LoadErrorEvent> {
+        match self.0.lock() {
+            Ok(mut errors) => errors.drain(..).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+// All errors since the level was loaded, shown in the errors window.
+#[derive(Resource, Default)]
+pub struct LoadErrors {
+    pub errors: Vec<LoadErrorEvent>,
+}
+
+pub(crate) fn process_load_errors(
+    queue: Res<LoadErrorQueue>,
+    mut reader: EventReader<LoadErrorEvent>,
+    mut load_errors: ResMut<LoadErrors>,
+) {
+    for error in queue.drain().into_iter().chain(reader.iter().cloned()) {
+        log::error!("load error: {}", error);
+
+        load_errors.errors.push(error);
+    }
+}
@@ -0,0 +1,298 @@
+use std::path::{Path, PathBuf};
+use bevy::{log, prelude::*};
+use bevy_mod_picking::highlight::InitialHighlight;
+use bevy_mod_picking::prelude::Highlight;
+use ::serde::{Deserialize, Serialize};
+
+use crate::gui::FileState;
+use super::collider::{FROM_FLOAT, TO_FLOAT};
+use super::Object;
+
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum MaterialAlphaMode {
+    #[default]
+    Opaque,
+    Mask(u32), // cutoff
+    Blend,
+    Premultiplied,
+    Add,
+    Multiply,
+}
+
+impl ToString for MaterialAlphaMode {
+    fn to_string(&self) -> String {
+        return match self {
+            MaterialAlphaMode::Opaque => "opaque".to_string(),
+            MaterialAlphaMode::Mask(_) => "mask".to_string(),
+            MaterialAlphaMode::Blend => "blend".to_string(),
+            MaterialAlphaMode::Premultiplied => "premultiplied".to_string(),
+            MaterialAlphaMode::Add => "add".to_string(),
+            MaterialAlphaMode::Multiply => "multiply".to_string(),
+        };
+    }
+}
+
+impl MaterialAlphaMode {
+    pub fn all() -> Vec<MaterialAlphaMode> {
+        vec![
+            MaterialAlphaMode::Opaque,
+            MaterialAlphaMode::Mask((0.5 * TO_FLOAT) as u32),
+            MaterialAlphaMode::Blend,
+            MaterialAlphaMode::Premultiplied,
+            MaterialAlphaMode::Add,
+            MaterialAlphaMode::Multiply,
+        ]
+    }
+
+    pub fn to_alpha_mode(&self) -> AlphaMode {
+        match *self {
+            MaterialAlphaMode::Opaque => AlphaMode::Opaque,
+            MaterialAlphaMode::Mask(cutoff) => AlphaMode::Mask(cutoff as f32 * FROM_FLOAT),
+            MaterialAlphaMode::Blend => AlphaMode::Blend,
+            MaterialAlphaMode::Premultiplied => AlphaMode::Premultiplied,
+            MaterialAlphaMode::Add => AlphaMode::Add,
+            MaterialAlphaMode::Multiply => AlphaMode::Multiply,
+        }
+    }
+}
+
+// Replaces the materials of all meshes of the object, nested objects keep their own.
+// Fixed point values like `LightData`, the textures are asset paths.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct MaterialOverride {
+    pub base_color: [u32; 4],
+    pub metallic: u32,
+    pub roughness: u32,
+    pub emissive: [u32; 4],
+    pub base_color_texture: Option<PathBuf>,
+    pub metallic_roughness_texture: Option<PathBuf>,
+    pub normal_map_texture: Option<PathBuf>,
+    pub emissive_texture: Option<PathBuf>,
+    pub alpha_mode: MaterialAlphaMode,
+}
+
+// Bevy defaults of `StandardMaterial`.
+impl Default for MaterialOverride {
+    fn default() -> Self {
+        let material = StandardMaterial::default();
+
+        let mut data = Self {
+            base_color: [0; 4],
+            metallic: 0,
+            roughness: 0,
+            emissive: [0; 4],
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            normal_map_texture: None,
+            emissive_texture: None,
+            alpha_mode: MaterialAlphaMode::Opaque,
+        };
+
+        data.set_base_color(material.base_color);
+        data.set_metallic(material.metallic);
+        data.set_roughness(material.perceptual_roughness);
+        data.set_emissive(material.emissive);
+
+        data
+    }
+}
+
+impl MaterialOverride {
+    pub fn get_base_color(&self) -> Color { fixed_to_color(&self.base_color) }
+    pub fn set_base_color(&mut self, color: Color) { self.base_color = color_to_fixed(color); }
+
+    pub fn get_metallic(&self) -> f32 { self.metallic as f32 * FROM_FLOAT }
+    pub fn set_metallic(&mut self, value: f32) { self.metallic = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_roughness(&self) -> f32 { self.roughness as f32 * FROM_FLOAT }
+    pub fn set_roughness(&mut self, value: f32) { self.roughness = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_emissive(&self) -> Color { fixed_to_color(&self.emissive) }
+    pub fn set_emissive(&mut self, color: Color) { self.emissive = color_to_fixed(color); }
+
+    // `load_texture` loads an asset path, the editor and games resolve asset roots differently.
+    pub fn to_material(&self, mut load_texture: impl FnMut(&Path) -> Handle<Image>) -> StandardMaterial {
+        StandardMaterial {
+            base_color: self.get_base_color(),
+            base_color_texture: self.base_color_texture.as_deref().map(&mut load_texture),
+            metallic: self.get_metallic(),
+            perceptual_roughness: self.get_roughness(),
+            metallic_roughness_texture: self.metallic_roughness_texture.as_deref().map(&mut load_texture),
+            normal_map_texture: self.normal_map_texture.as_deref().map(&mut load_texture),
+            emissive: self.get_emissive(),
+            emissive_texture: self.emissive_texture.as_deref().map(&mut load_texture),
+            alpha_mode: self.alpha_mode.to_alpha_mode(),
+            ..default()
+        }
+    }
+}
+
+fn fixed_to_color(color: &[u32; 4]) -> Color {
+    Color::rgba(
+        color[0] as f32 * FROM_FLOAT,
+        color[1] as f32 * FROM_FLOAT,
+        color[2] as f32 * FROM_FLOAT,
+        color[3] as f32 * FROM_FLOAT,
+    )
+}
+
+fn color_to_fixed(color: Color) -> [u32; 4] {
+    color.as_rgba_f32().map(|value| (value * TO_FLOAT).round() as u32)
+}
+
+// The material of the override, shared by all meshes of the object and changed in place,
+// so that edits in the right panel are shown at once.
+#[derive(Component)]
+pub(crate) struct MaterialOverrideHandle(Handle<StandardMaterial>);
+
+// The material of a mesh before the override, restored when the override is removed.
+#[derive(Component)]
+pub(crate) struct OriginalMaterial(Handle<StandardMaterial>);
+
+type MeshMaterialQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Handle<StandardMaterial>, Option<&'static mut InitialHighlight<StandardMaterial>>),
+    Without<Highlight<StandardMaterial>>,
+>;
+
+pub(crate) fn process_material_overrides(
+    mut commands: Commands,
+    object_query: Query<(Entity, &Object, Option<&MaterialOverrideHandle>), Changed<Object>>,
+    children_query: Query<&Children>,
+    nested_query: Query<(), With<Object>>,
+    original_query: Query<&OriginalMaterial>,
+    mut mesh_query: MeshMaterialQuery,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    file_state: Res<FileState>,
+) {
+    for (entity, object, override_handle) in object_query.iter() {
+        let mut meshes = Vec::new();
+        collect_object_meshes(entity, &children_query, &nested_query, &mut meshes);
+
+        let material_override = match object.material.as_ref() {
+            Some(material_override) => material_override,
+            None => {
+                if override_handle.is_none() {
+                    continue;
+                }
+
+                log::info!("process_material_overrides: restore {:?}", entity);
+
+                for mesh in meshes.into_iter() {
+                    if let Ok(original) = original_query.get(mesh) {
+                        set_mesh_material(&mut mesh_query, mesh, &original.0);
+                        commands.entity(mesh).remove::<OriginalMaterial>();
+                    }
+                }
+
+                commands.entity(entity).remove::<MaterialOverrideHandle>();
+                continue;
+            },
+        };
+
+        let material = material_override.to_material(|path| asset_server.load(file_state.to_load_path(path)));
+
+        let handle = match override_handle {
+            Some(MaterialOverrideHandle(handle)) => {
+                if let Some(current) = materials.get_mut(handle) {
+                    *current = material;
+                }
+                handle.clone()
+            },
+            None => {
+                let handle = materials.add(material);
+                commands.entity(entity).insert(MaterialOverrideHandle(handle.clone()));
+                handle
+            },
+        };
+
+        // The meshes are replaced when the object is added again.
+        for mesh in meshes.into_iter() {
+            override_mesh_material(&mut commands, &mut mesh_query, mesh, &handle);
+        }
+    }
+}
+
+// Meshes of gltf scenes and highlight materials appear after the object is changed.
+// The filters read the materials, so the queries are in a set.
+pub(crate) fn process_new_mesh_materials(
+    mut commands: Commands,
+    mut mesh_queries: ParamSet<(
+        Query<
+            Entity,
+            (
+                Or<(Added<Handle<StandardMaterial>>, Added<InitialHighlight<StandardMaterial>>)>,
+                Without<Highlight<StandardMaterial>>,
+            ),
+        >,
+        MeshMaterialQuery,
+    )>,
+    parent_query: Query<&Parent>,
+    object_query: Query<Option<&MaterialOverrideHandle>, With<Object>>,
+) {
+    let new_meshes: Vec<Entity> = mesh_queries.p0().iter().collect();
+
+    for mesh in new_meshes.into_iter() {
+        let mut current = mesh;
+
+        // The nearest object owns the mesh.
+        while !object_query.contains(current) {
+            current = match parent_query.get(current) {
+                Ok(parent) => parent.get(),
+                Err(_) => break,
+            };
+        }
+
+        if let Ok(Some(MaterialOverrideHandle(handle))) = object_query.get(current) {
+            override_mesh_material(&mut commands, &mut mesh_queries.p1(), mesh, handle);
+        }
+    }
+}
+
+fn collect_object_meshes(
+    entity: Entity,
+    children_query: &Query<&Children>,
+    nested_query: &Query<(), With<Object>>,
+    meshes: &mut Vec<Entity>,
+) {
+    meshes.push(entity);
+
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter().filter(|child| !nested_query.contains(**child)) {
+            collect_object_meshes(*child, children_query, nested_query, meshes);
+        }
+    }
+}
+
+// Bevy picking swaps the material of hovered and selected meshes and restores
+// `InitialHighlight`, so the override is written there too.
+fn override_mesh_material(
+    commands: &mut Commands,
+    mesh_query: &mut MeshMaterialQuery,
+    mesh: Entity,
+    handle: &Handle<StandardMaterial>,
+) {
+    let (material, initial) = crate::if_err_return!(mesh_query.get(mesh));
+
+    let current = initial.map_or(material.clone(), |initial| initial.initial.clone());
+
+    if current == *handle {
+        return;
+    }
+
+    commands.entity(mesh).insert(OriginalMaterial(current));
+
+    set_mesh_material(mesh_query, mesh, handle);
+}
+
+fn set_mesh_material(mesh_query: &mut MeshMaterialQuery, mesh: Entity, handle: &Handle<StandardMaterial>) {
+    let (mut material, initial) = crate::if_err_return!(mesh_query.get_mut(mesh));
+
+    *material = handle.clone();
+
+    if let Some(mut initial) = initial {
+        initial.initial = handle.clone();
+    }
+}
@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+use bevy::asset::LoadState;
+use bevy::{log, prelude::*};
+use bevy_mod_picking::prelude::Highlight;
+
+use crate::gui::{FileState, MyEditorState};
+
+use super::{is_level_path, AddObjectEvent, EditorId, LoadErrorEvent, LoadObjectEvent, Object, RonOverride};
+use super::spawn::{Resources, HIGHLIGHT_TINT};
+
+// The file of the object is missing, a placeholder is shown instead.
+// The overrides of a nested ron are kept until it is relinked.
+#[derive(Component, Debug, Clone, Default)]
+pub struct MissingAsset {
+    pub overrides: Vec<RonOverride>,
+}
+
+// Points every missing object with the `from` path to the `to` file.
+pub struct RelinkAssetEvent {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+pub(crate) fn is_missing(file_state: &FileState, path: &Path) -> bool {
+    file_state.to_file_path(path).map_or(true, |file_path| !file_path.exists())
+}
+
+pub(crate) fn insert_placeholder(
+    entity_commands: &mut bevy::ecs::system::EntityCommands,
+    transform: Transform,
+    overrides: Vec<RonOverride>,
+    resources: &Resources,
+) {
+    entity_commands.insert((
+        PbrBundle {
+            mesh: resources.placeholder_mesh.clone().unwrap_or_default(),
+            material: resources.placeholder_material.clone().unwrap_or_default(),
+            transform,
+            ..default()
+        },
+        HIGHLIGHT_TINT.clone(),
+        MissingAsset { overrides },
+    ));
+}
+
+pub(crate) struct PendingRelink {
+    from: PathBuf,
+    to: PathBuf,
+    handle: HandleUntyped,
+}
+
+// The objects are added again once the new file is loaded, nested files of a ron object
+// are loaded like the files of a level.
+pub(crate) fn process_relink_asset(
+    mut commands: Commands,
+    mut reader: EventReader<RelinkAssetEvent>,
+    asset_server: Res<AssetServer>,
+    file_state: Res<FileState>,
+    mut editor_state: ResMut<MyEditorState>,
+    missing_query: Query<(Entity, &Object, &EditorId, &Transform, &MissingAsset)>,
+    mut pending: Local<Vec<PendingRelink>>,
+    mut load_writer: EventWriter<LoadObjectEvent>,
+    mut writer: EventWriter<AddObjectEvent>,
+    mut error_writer: EventWriter<LoadErrorEvent>,
+) {
+    for RelinkAssetEvent { from, to } in reader.iter() {
+        log::info!("process_relink_asset {:?} -> {:?}", from, to);
+
+        if is_level_path(to) {
+            load_writer.send(LoadObjectEvent { path: to.clone() });
+        }
+
+        pending.push(PendingRelink {
+            from: from.clone(),
+            to: to.clone(),
+            handle: asset_server.load_untyped(file_state.to_load_path(to)),
+        });
+    }
+
+    let mut waiting = Vec::new();
+
+    for relink in pending.drain(..) {
+        match asset_server.get_load_state(&relink.handle) {
+            LoadState::Loaded if file_state.qnt_loading_ogjects == 0 => (),
+            LoadState::Failed => {
+                error_writer.send(LoadErrorEvent::new(
+                    Some(relink.to),
+                    None,
+                    "failed to load the relinked file, see the log for details",
+                ));
+                continue;
+            },
+            _ => {
+                waiting.push(relink);
+                continue;
+            },
+        };
+
+        let PendingRelink { from, to, .. } = relink;
+
+        for (entity, object, id, transform, missing) in missing_query.iter() {
+            if object.path.as_ref() != Some(&from) {
+                continue;
+            }
+
+            commands.entity(entity).remove::<(
+                MissingAsset,
+                Handle<Mesh>,
+                Handle<StandardMaterial>,
+                Highlight<StandardMaterial>,
+            )>();
+
+            writer.send(AddObjectEvent {
+                entity: Some(entity),
+                id: Some(*id),
+                object: Some(Object {
+                    path: Some(to.clone()),
+                    ..object.clone()
+                }),
+                transform: Some(*transform),
+                selected: false,
+                overrides: missing.overrides.clone(),
+            });
+        }
+
+        let MyEditorState { objects, selected_object } = &mut *editor_state;
+
+        for object in objects.values_mut().chain(selected_object.as_mut()) {
+            if object.path.as_ref() == Some(&from) {
+                object.path = Some(to.clone());
+            }
+        }
+    }
+
+    *pending = waiting;
+}
@@ -6,6 +6,7 @@ use bevy_gltf::{GltfMesh, GltfNode};
 use bevy_transform_gizmo::GizmoTransformable;
 use ::serde::{Serialize, Deserialize, de::DeserializeSeed};
 use bevy::log;
+use bevy::utils::HashMap;
 use bevy::{
     reflect::{
         serde::{ReflectSerializer, UntypedReflectDeserializer},
@@ -16,24 +17,59 @@ use crate::gui::{FileState, SelectState};
 use crate::picking::{ObjectRaycastSet, PickingEvent};
 use crate::{if_none_return, if_none_continue, if_err_return};
 
+pub use self::asset_path::*;
 pub use self::collider::*;
-use self::gltf::{process_add_gltf_scene, process_add_gltf_mesh};
+pub use self::id::*;
+pub use self::load_error::{LoadErrorEvent, LoadErrorQueue, LoadErrors};
+pub use self::missing::{MissingAsset, RelinkAssetEvent};
+use self::missing::{is_missing, insert_placeholder, process_relink_asset};
+use self::load_error::process_load_errors;
+pub use self::importer::{ObjectImporter, ObjectImporters, ImportedAsset, ImportObjectEvent, GLTF_IMPORTER};
+use self::importer::process_import_scene_files;
+use self::gltf::PendingGltfs;
+pub use self::light::{LightData, LightType, insert_light_components};
+use self::light::{insert_light_icon, process_lights};
+pub use self::primitive::{Primitive, PrimitiveShape, insert_primitive};
+use self::primitive::{insert_primitive_bundle, process_primitives};
+pub use self::material::{MaterialOverride, MaterialAlphaMode};
+use self::material::{process_material_overrides, process_new_mesh_materials};
+pub use self::particles::{ParticleData, ParticleColorKey, ParticleSizeKey, spawn_particle_effect};
+use self::particles::{insert_emitter_icon, process_particles};
+use self::gltf::{process_add_gltf_scene, process_add_gltf_mesh, process_add_gltf_node, process_load_gltf};
+pub use self::gltf_export::ExportGltfEvent;
+pub use self::dynamic_scene::{LevelObject, LevelObjectPlugin, LevelAssetRoots, ImportSceneEvent, ExportSceneEvent};
+use self::dynamic_scene::*;
+use self::gltf_export::process_export_gltf;
 pub use self::ron::*;
 pub use self::spawn::CompositeObjectLabel;
 use self::spawn::*;
 
 
 mod ron;
+mod asset_path;
+mod id;
+mod load_error;
+mod missing;
 mod gltf;
+mod gltf_export;
+mod importer;
+mod light;
+mod primitive;
+mod material;
+mod particles;
+mod dynamic_scene;
 mod spawn;
 mod collider;
 
 #[derive(Clone)]
 pub struct AddObjectEvent {
     pub entity: Option<Entity>,
+    pub id: Option<EditorId>,
     pub object: Option<Object>,
     pub transform: Option<Transform>,
     pub selected: bool,
+    // Overrides of a nested ron object.
+    pub overrides: Vec<RonOverride>,
 }
 
 #[derive(Clone)]
@@ -64,6 +100,14 @@ pub struct AddGltfMeshEvent {
     pub transform: Transform,
 }
 
+#[derive(Clone)]
+pub struct AddGltfNodeEvent {
+    pub entity: Entity,
+    pub handle: Handle<GltfNode>,
+    pub collider: Option<Collider>,
+    pub transform: Transform,
+}
+
 pub struct LoadObjectEvent {
  //   pub object: Option<ObjectType>,   
     pub path: PathBuf,
@@ -72,10 +116,12 @@ pub struct LoadObjectEvent {
 #[derive(Default, Debug, Resource, Component)]
 struct LoadedObjects {
     pub handles: Vec<HandleId>,   
+    // Asset paths of the loading files, for the dependency graph.
+    pub paths: HashMap<HandleId, PathBuf>,
 }
 
 
-#[derive(Default, Debug, Clone, Eq, PartialEq, Hash, Resource, Component, Reflect, FromReflect, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Resource, Component, Reflect, FromReflect, Serialize, Deserialize)]
 #[reflect(Resource, Serialize, Deserialize)]
 pub enum ObjectType {
     #[default]
@@ -84,15 +130,62 @@ pub enum ObjectType {
     Mesh,
     Ron,
     Collider,
+    Node,
+    Light,
+    Primitive,
+    Particles,
 }
 
 
-#[derive(Default, Debug, Clone, Eq, PartialEq, Hash, Resource, Component, Reflect, FromReflect, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Resource, Component, Reflect, FromReflect, Serialize, Deserialize)]
 #[reflect(Resource, Serialize, Deserialize)]
 pub struct Object {
     pub object_type: ObjectType,
     pub path: Option<PathBuf>,
     pub collider: Option<Collider>,    
+    // Sub-asset of a gltf file, like `Scene1`, `Mesh2` or `Node3`. Objects saved without it use
+    // the first scene or mesh.
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub light: Option<LightData>,
+    #[serde(default)]
+    pub primitive: Option<Primitive>,
+    #[serde(default)]
+    pub material: Option<MaterialOverride>,
+    #[serde(default)]
+    pub particles: Option<ParticleData>,
+}
+
+impl Object {
+    // Why the object can't be spawned, `None` if it can.
+    pub fn missing_data(&self) -> Option<String> {
+        match self.object_type {
+            ObjectType::Scene | ObjectType::Mesh | ObjectType::Node | ObjectType::Ron if self.path.is_none() =>
+                Some(format!("{:?} object has no path", self.object_type)),
+            ObjectType::Collider if self.collider.is_none() =>
+                Some("Collider object has no collider".to_string()),
+            ObjectType::Node if self.label.is_none() =>
+                Some("Node object has no label".to_string()),
+            ObjectType::Light if self.light.is_none() =>
+                Some("Light object has no light".to_string()),
+            ObjectType::Primitive if self.primitive.is_none() =>
+                Some("Primitive object has no shape".to_string()),
+            ObjectType::Particles if self.particles.is_none() =>
+                Some("Particles object has no effect".to_string()),
+            _ => None,
+        }
+    }
+
+    // The gltf sub-asset to load, `None` for other objects.
+    pub fn gltf_label(&self) -> Option<String> {
+        match self.object_type {
+            ObjectType::Scene => Some(self.label.clone().unwrap_or("Scene0".to_string())),
+            ObjectType::Mesh => Some(self.label.clone().unwrap_or("Mesh0".to_string())),
+            ObjectType::Node => self.label.clone(),
+            _ => None,
+        }
+    }
 }
 
 
@@ -116,15 +209,38 @@ impl Plugin for ObjectPlugin {
         app
             .insert_resource(LoadedObjects::default())
             .insert_resource(Resources::default())
+            .init_resource::<EditorIdMap>()
+            .init_resource::<PendingScenes>()
+            .init_resource::<PendingGltfs>()
+            .init_resource::<ObjectImporters>()
+            .init_resource::<LoadErrors>()
+            .register_type::<EditorId>()
+            .register_type::<LevelObject>()
             .add_event::<LoadObjectEvent>()   
+            .add_event::<LoadErrorEvent>()
+            .add_event::<RelinkAssetEvent>()
             .add_event::<AddObjectEvent>()     
             .add_event::<AddGltfSceneEvent>()    
             .add_event::<ProcessNewMeshEvent>()  
             .add_event::<SetPickableMeshWaiterEvent>()  
             .add_event::<AddGltfMeshEvent>()    
+            .add_event::<AddGltfNodeEvent>()
+            .add_event::<ImportObjectEvent>()
+            .add_event::<ExportGltfEvent>()
+            .add_event::<ImportSceneEvent>()
+            .add_event::<ExportSceneEvent>()
             .add_plugin(RonPlugin)    
             .add_plugin(ColliderPlugin)  
             .add_startup_system(setup_spawn_resources)  
+            .add_system(process_editor_ids)
+            .add_system(process_import_scene_files)
+            .add_system(process_load_errors)
+            .add_system(process_export_gltf)
+            .add_systems((
+                process_export_scene,
+                process_import_scene,
+                spawn_imported_scenes.after(process_import_scene),
+            ))
             .add_system(process_load_object.before(check_load_objects_complete))
             .add_system(check_load_objects_complete.after(process_load_object))
             .add_systems((
@@ -133,11 +249,21 @@ impl Plugin for ObjectPlugin {
             ))
             .add_systems((                
                 process_add_object,
+                process_relink_asset.before(process_add_object),
                 process_add_gltf_scene.after(process_add_object),
                 process_add_gltf_mesh.after(process_add_object),
+                process_add_gltf_node.after(process_add_object),
+                process_load_gltf,
+                process_lights.after(process_add_object),
+                process_primitives.after(process_add_object),
                 process_set_pickable_mesh.after(process_add_gltf_scene),
                 await_set_pickable_mesh.after(process_set_pickable_mesh),
             ))
+            .add_systems((
+                process_material_overrides.after(process_add_object),
+                process_new_mesh_materials.after(process_material_overrides),
+                process_particles.after(process_add_object),
+            ))
             ;
     }
 }  
@@ -145,12 +271,22 @@ impl Plugin for ObjectPlugin {
 fn process_load_object(
     mut reader: EventReader<LoadObjectEvent>,
     asset_server: Res<AssetServer>,
+    file_state: Res<FileState>,
     mut load_data: ResMut<LoadedObjects>,
 ) {
     for LoadObjectEvent { path } in reader.iter() {
-        let handle: Handle<Scene> = asset_server.load(path.display().to_string());
+        let handle: Handle<Scene> = asset_server.load(file_state.to_load_path(path));
         load_data.handles.push(handle.id());
 
+        // The level itself is loaded by its absolute path.
+        let asset_path = if path.is_absolute() {
+            file_state.to_asset_path(path).unwrap_or(path.clone())
+        } else {
+            path.clone()
+        };
+
+        load_data.paths.insert(handle.id(), asset_path);
+
         log::info!("process_load_object {:?}, {}", path.display().to_string(), load_data.handles.len());
     }
 
@@ -184,60 +320,125 @@ fn process_load_object(
 fn check_load_objects_complete(
     mut state: ResMut<FileState>,
     mut load_data: ResMut<LoadedObjects>,
+    mut dependencies: ResMut<RonDependencies>,
     asset_server: Res<AssetServer>,
     ron_assets: Res<Assets<Ron>>,
     mut writer: EventWriter<LoadObjectEvent>,
+    mut error_writer: EventWriter<LoadErrorEvent>,
 ) {
-    load_data.handles = load_data
-        .handles
-        .iter()
-        .filter(|&&handle| {
-            return match asset_server.get_load_state(handle) {                
-                LoadState::NotLoaded => true,
-                LoadState::Loading => true,
-                LoadState::Loaded => {                    
-                    let handle = asset_server.get_handle(handle);
-
-                    if let Some(ron) = ron_assets.get(&handle) {
-
-                        log::info!("check_load_objects_complete ron ok");
-
-                        for (_, object) in ron.objects.iter() {
-                            if let Some(path) = object.path.clone() {
-                                writer.send(LoadObjectEvent{path});
-                            }
-                        }
-                    }
+    let mut handles = Vec::new();
 
-                    false
-                },
-                LoadState::Failed => false,
-                LoadState::Unloaded => true, 
-            };
-        } )
-        .map(|handle| *handle)
-        .collect::<Vec<HandleId>>();
+    for handle_id in load_data.handles.clone().into_iter() {
+        match asset_server.get_load_state(handle_id) {
+            LoadState::NotLoaded | LoadState::Loading | LoadState::Unloaded => {
+                handles.push(handle_id);
+                continue;
+            },
+            LoadState::Loaded | LoadState::Failed => (),
+        };
+
+        let path = load_data.paths.remove(&handle_id);
+
+        // Failed levels are reported by `RonLoader` with the reason.
+        if asset_server.get_load_state(handle_id) == LoadState::Failed {
+            if let Some(path) = path.as_ref().filter(|path| !is_level_path(path)) {
+                error_writer.send(LoadErrorEvent::new(Some(path.clone()), None, "failed to load the file, see the log for details"));
+            }
+            continue;
+        }
+
+        let handle = asset_server.get_handle(handle_id);
+        let ron = if_none_continue!(ron_assets.get(&handle));
+
+        log::info!("check_load_objects_complete ron ok");
+
+        let path = if_none_continue!(path);
+
+        // Layer files are next to the master level.
+        let layers: Vec<PathBuf> = match state.to_file_path(&path).and_then(|file| file.parent().map(|parent| parent.to_path_buf())) {
+            Some(directory) => ron.layers.iter().map(|layer| directory.join(&layer.path)).collect(),
+            None => Vec::new(),
+        };
+
+        dependencies.insert(
+            &path,
+            ron.objects
+                .iter()
+                .filter_map(|object| object.path.clone())
+                .chain(layers.iter().map(|layer| state.to_asset_path(layer).unwrap_or(layer.clone())))
+                .collect(),
+        );
+
+        for layer in layers.into_iter() {
+            writer.send(LoadObjectEvent{path: layer});
+        }
+
+        for object in ron.objects.iter() {
+            let object_path = if_none_continue!(object.path.clone());
+
+            // A level that uses itself would be loaded again and again.
+            if object.object_type == ObjectType::Ron {
+                if let Some(chain) = dependencies.find_cycle(&path, &object_path) {
+                    report_cycle(&mut dependencies, chain);
+                    continue;
+                }
+            }
+
+            writer.send(LoadObjectEvent{path: object_path});
+        }
+    }
+
+    load_data.handles = handles;
 
     state.qnt_loading_ogjects = load_data.handles.len();
 }
 
+fn is_level_path(path: &std::path::Path) -> bool {
+    path.extension().map_or(false, |extension| extension == "ron" || extension == BINARY_EXTENSION)
+}
+
+fn report_cycle(dependencies: &mut RonDependencies, chain: Vec<PathBuf>) {
+    let description = format_chain(&chain);
+
+    if !dependencies.add_cycle(chain) {
+        return;
+    }
+
+    log::error!("cyclic level reference: {}", description);
+
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title("Cyclic level reference")
+        .set_description(&("The level uses itself: ".to_string() + &description + ". The cyclic nested levels are not spawned."))
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
 
-pub fn process_add_object(
+
+pub(crate) fn process_add_object(
     mut commands: Commands,
 //    mut state: ResMut<SelectState>,
     mut reader: EventReader<AddObjectEvent>,
     asset_server: Res<AssetServer>,
+    file_state: Res<FileState>,
+    dependencies: Res<RonDependencies>,
+    resources: Res<Resources>,
+    importers: Res<ObjectImporters>,
     mut gltf_scene_writer: EventWriter<AddGltfSceneEvent>,
     mut gltf_mesh_writer: EventWriter<AddGltfMeshEvent>,
+    mut gltf_node_writer: EventWriter<AddGltfNodeEvent>,
     mut ron_writer: EventWriter<AddRonEvent>,
     mut collider_writer: EventWriter<CreateColliderEvent>,
     mut picking_writer: EventWriter<PickingEvent>,
+    mut error_writer: EventWriter<LoadErrorEvent>,
 ) {
     for AddObjectEvent {
         entity,
+        id,
         object,
         transform,
         selected,
+        overrides,
     } in reader.iter() {
         log::info!("process_add_object");
 
@@ -251,17 +452,40 @@ pub fn process_add_object(
 
         let entity = if_none_return!(entity.clone());
 
+        let id = id.unwrap_or_default();
+
         if let Some(mut entity_commands) = commands.get_entity(entity) {
             entity_commands
                 .insert(object.clone())
+                .insert(id)
                 .insert(GizmoTransformable);
 
+            // Kept as an empty node, the object is still saved.
+            if let Some(reason) = object.missing_data() {
+                log::error!("process_add_object: {}", reason);
+
+                entity_commands.insert(SpatialBundle {
+                    transform,
+                    ..Default::default()
+                });
+                continue;
+            }
+
+            if let Some(path) = object.path.as_ref().filter(|path| is_missing(&file_state, path)) {
+                error_writer.send(LoadErrorEvent::new(Some(path.clone()), Some(id), "the file of the object is missing"));
+
+                insert_placeholder(&mut entity_commands, transform, overrides.clone(), &resources);
+                continue;
+            }
+
+            let label = importers.asset_label(&object).map_or(String::new(), |label| "#".to_string() + &label);
+
             match object.object_type {
                 ObjectType::Scene => {
                     gltf_scene_writer.send(AddGltfSceneEvent {
                         entity,
                         collider: object.collider,
-                        handle: asset_server.load(object.path.expect("process_add_object no path!").display().to_string() + "#Scene0"),
+                        handle: asset_server.load(file_state.to_load_path(&object.path.unwrap_or_default()) + &label),
                         transform,
                     });
                 },
@@ -270,27 +494,63 @@ pub fn process_add_object(
                     gltf_mesh_writer.send(AddGltfMeshEvent {
                         entity,
                         collider: object.collider,
-                        handle: asset_server.load(object.path.expect("process_add_object no path!").display().to_string() + "#Mesh0"),
+                        handle: asset_server.load(file_state.to_load_path(&object.path.unwrap_or_default()) + &label),
+                        transform,
+                    });
+                },
+
+                ObjectType::Node => {
+                    gltf_node_writer.send(AddGltfNodeEvent {
+                        entity,
+                        collider: object.collider,
+                        handle: asset_server.load(file_state.to_load_path(&object.path.unwrap_or_default()) + &label),
                         transform,
                     });
                 },
 
                 ObjectType::Ron => {
+                    let path = object.path.unwrap_or_default();
+
+                    // Kept as an empty node, so that saving doesn't lose the reference.
+                    if dependencies.is_cyclic(&path) {
+                        log::error!("process_add_object: {} is part of a cyclic level reference", path.display());
+
+                        entity_commands.insert(SpatialBundle {
+                            transform,
+                            ..Default::default()
+                        });
+                        continue;
+                    }
+
                     ron_writer.send(AddRonEvent {
                         entity,
-                        handle: asset_server.load(object.path.expect("process_add_object no path!").display().to_string()),
+                        id,
+                        handle: asset_server.load(file_state.to_load_path(&path)),
                         transform,
+                        overrides: overrides.clone(),
                     });
                 },
 
                 ObjectType::Collider => {
                     collider_writer.send(CreateColliderEvent {
                         entity,
-                        collider: object.collider.expect("process_add_object no collider!"),
+                        collider: object.collider.unwrap_or_default(),
                         transform: Some(transform),
                     });
                 },
                 
+                ObjectType::Light => {
+                    insert_light_icon(&mut entity_commands, transform, &resources);
+                },
+
+                ObjectType::Primitive => {
+                    insert_primitive_bundle(&mut entity_commands, transform, &resources);
+                },
+
+                ObjectType::Particles => {
+                    insert_emitter_icon(&mut entity_commands, transform, &resources);
+                },
+
                 ObjectType::Empty => {
                     entity_commands.insert(SpatialBundle {
                         transform,
@@ -309,18 +569,29 @@ pub fn process_add_object(
 fn process_load_ron (
     mut reader: EventReader<LoadRonEvent>,
     mut state: ResMut<FileState>,
+    mut dependencies: ResMut<RonDependencies>,
+    mut load_errors: ResMut<LoadErrors>,
     asset_server: Res<AssetServer>,
+    mut writer: EventWriter<LoadObjectEvent>,
 ) {
     let LoadRonEvent {path} = if_none_return!(reader.iter().last());
 
     log::info!("process_load_ron");   
 
-    state.load_handle = Some(asset_server.load(path.clone().expect("process_load_ron path err").display().to_string()));  
-    state.current_file_path = path.clone();
+    let path = path.clone().expect("process_load_ron path err");
+
+    state.load_handle = Some(asset_server.load(path.display().to_string()));  
+    state.current_file_path = Some(path.clone());
+
+    dependencies.clear();
+    load_errors.errors.clear();
+
+    writer.send(LoadObjectEvent { path });
 }
 
 fn check_load_ron (
     mut state: ResMut<FileState>,
+    asset_server: Res<AssetServer>,
     mut writer: EventWriter<SpawnRonEvent>,
 ) {  
     if state.qnt_loading_ogjects != 0 {
@@ -329,6 +600,24 @@ fn check_load_ron (
 
     let handle = if_none_return!(state.load_handle.clone());
 
+    match asset_server.get_load_state(&handle) {
+        LoadState::Loaded => (),
+        LoadState::Failed => {
+            state.load_handle = None;
+
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Error)
+                .set_title("Load level error")
+                .set_description(&("Failed to load ".to_string() + &state.get_file_name() + ", see the log for details."))
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
+
+            state.current_file_path = None;
+            return;
+        },
+        _ => return,
+    }
+
     state.load_handle = None;    
 
     writer.send(SpawnRonEvent { handle });
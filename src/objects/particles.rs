@@ -0,0 +1,245 @@
+use bevy::{log, prelude::*};
+use bevy::pbr::NotShadowCaster;
+use bevy_hanabi::prelude::*;
+use ::serde::{Deserialize, Serialize};
+
+use super::collider::{FROM_FLOAT, TO_FLOAT};
+use super::spawn::{Resources, HIGHLIGHT_TINT};
+use super::{Object, ObjectType};
+
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct ParticleColorKey {
+    pub ratio: u32,
+    pub color: [u32; 4],
+}
+
+impl ParticleColorKey {
+    pub fn new(ratio: f32, color: Color) -> Self {
+        Self {
+            ratio: (ratio * TO_FLOAT).round() as u32,
+            color: color.as_rgba_f32().map(|value| (value * TO_FLOAT).round() as u32),
+        }
+    }
+
+    pub fn get_ratio(&self) -> f32 { self.ratio as f32 * FROM_FLOAT }
+    pub fn set_ratio(&mut self, value: f32) { self.ratio = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_color(&self) -> Color {
+        Color::rgba(
+            self.color[0] as f32 * FROM_FLOAT,
+            self.color[1] as f32 * FROM_FLOAT,
+            self.color[2] as f32 * FROM_FLOAT,
+            self.color[3] as f32 * FROM_FLOAT,
+        )
+    }
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color.as_rgba_f32().map(|value| (value * TO_FLOAT).round() as u32);
+    }
+}
+
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct ParticleSizeKey {
+    pub ratio: u32,
+    pub size: u32,
+}
+
+impl ParticleSizeKey {
+    pub fn new(ratio: f32, size: f32) -> Self {
+        Self {
+            ratio: (ratio * TO_FLOAT).round() as u32,
+            size: (size * TO_FLOAT).round() as u32,
+        }
+    }
+
+    pub fn get_ratio(&self) -> f32 { self.ratio as f32 * FROM_FLOAT }
+    pub fn set_ratio(&mut self, value: f32) { self.ratio = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_size(&self) -> f32 { self.size as f32 * FROM_FLOAT }
+    pub fn set_size(&mut self, value: f32) { self.size = (value * TO_FLOAT).round() as u32; }
+}
+
+// A bevy_hanabi effect, fixed point values like `LightData`. Particles start on a sphere of
+// `spawn_radius` and move away from its center with `speed`, the keys are over the lifetime
+// of a particle with ratios from 0 to 1.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct ParticleData {
+    pub capacity: u32,
+    pub spawn_rate: u32,
+    pub lifetime: u32,
+    pub spawn_radius: u32,
+    pub speed: u32,
+    pub acceleration: [i32; 3],
+    pub color_keys: Vec<ParticleColorKey>,
+    pub size_keys: Vec<ParticleSizeKey>,
+}
+
+impl Default for ParticleData {
+    fn default() -> Self {
+        let mut data = Self {
+            capacity: 4096,
+            spawn_rate: 0,
+            lifetime: 0,
+            spawn_radius: 0,
+            speed: 0,
+            acceleration: [0; 3],
+            color_keys: vec![
+                ParticleColorKey::new(0.0, Color::WHITE),
+                ParticleColorKey::new(1.0, Color::rgba(1.0, 1.0, 1.0, 0.0)),
+            ],
+            size_keys: vec![
+                ParticleSizeKey::new(0.0, 0.1),
+                ParticleSizeKey::new(1.0, 0.0),
+            ],
+        };
+
+        data.set_spawn_rate(50.0);
+        data.set_lifetime(2.0);
+        data.set_spawn_radius(0.1);
+        data.set_speed(1.0);
+        data.set_acceleration(Vec3::new(0.0, -1.0, 0.0));
+
+        data
+    }
+}
+
+impl ParticleData {
+    // Particles per second.
+    pub fn get_spawn_rate(&self) -> f32 { self.spawn_rate as f32 * FROM_FLOAT }
+    pub fn set_spawn_rate(&mut self, value: f32) { self.spawn_rate = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_lifetime(&self) -> f32 { self.lifetime as f32 * FROM_FLOAT }
+    pub fn set_lifetime(&mut self, value: f32) { self.lifetime = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_spawn_radius(&self) -> f32 { self.spawn_radius as f32 * FROM_FLOAT }
+    pub fn set_spawn_radius(&mut self, value: f32) { self.spawn_radius = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_speed(&self) -> f32 { self.speed as f32 * FROM_FLOAT }
+    pub fn set_speed(&mut self, value: f32) { self.speed = (value * TO_FLOAT).round() as u32; }
+
+    pub fn get_acceleration(&self) -> Vec3 {
+        Vec3::from_array(self.acceleration.map(|value| value as f32 * FROM_FLOAT))
+    }
+    pub fn set_acceleration(&mut self, value: Vec3) {
+        self.acceleration = value.to_array().map(|value| (value * TO_FLOAT).round() as i32);
+    }
+
+    pub fn to_effect_asset(&self, name: &str) -> EffectAsset {
+        let mut color_gradient = Gradient::new();
+
+        for key in self.sorted_color_keys().iter() {
+            color_gradient.add_key(key.get_ratio(), Vec4::from_array(key.get_color().as_rgba_f32()));
+        }
+
+        let mut size_gradient = Gradient::new();
+
+        for key in self.sorted_size_keys().iter() {
+            size_gradient.add_key(key.get_ratio(), Vec2::splat(key.get_size()));
+        }
+
+        EffectAsset {
+            name: name.to_string(),
+            capacity: self.capacity.max(1),
+            spawner: Spawner::rate(self.get_spawn_rate().into()),
+            ..Default::default()
+        }
+        .init(InitPositionSphereModifier {
+            center: Vec3::ZERO,
+            radius: self.get_spawn_radius(),
+            dimension: ShapeDimension::Volume,
+        })
+        .init(InitVelocitySphereModifier {
+            center: Vec3::ZERO,
+            speed: self.get_speed().into(),
+        })
+        .init(InitLifetimeModifier {
+            lifetime: self.get_lifetime().into(),
+        })
+        .update(AccelModifier::constant(self.get_acceleration()))
+        .render(ColorOverLifetimeModifier { gradient: color_gradient })
+        .render(SizeOverLifetimeModifier { gradient: size_gradient })
+    }
+
+    // Gradients need their keys in order, the panel edits them in place.
+    fn sorted_color_keys(&self) -> Vec<ParticleColorKey> {
+        let mut keys = self.color_keys.clone();
+        keys.sort_by_key(|key| key.ratio);
+        keys
+    }
+
+    fn sorted_size_keys(&self) -> Vec<ParticleSizeKey> {
+        let mut keys = self.size_keys.clone();
+        keys.sort_by_key(|key| key.ratio);
+        keys
+    }
+}
+
+// The effect is a child, so that an edited effect is spawned anew.
+#[derive(Component)]
+pub(crate) struct ParticleEmitterEffect;
+
+pub fn spawn_particle_effect(
+    commands: &mut Commands,
+    entity: Entity,
+    particles: &ParticleData,
+    effects: &mut Assets<EffectAsset>,
+) {
+    let effect = effects.add(particles.to_effect_asset("emitter"));
+
+    let mut entity_commands = crate::if_none_return!(commands.get_entity(entity));
+
+    entity_commands.with_children(|parent| {
+        parent.spawn((
+            ParticleEffectBundle {
+                effect: ParticleEffect::new(effect),
+                ..Default::default()
+            },
+            Name::new("particles"),
+            ParticleEmitterEffect,
+        ));
+    });
+}
+
+// Emitters have no mesh, a small cube shows and picks them in the viewport like the light icon.
+pub(crate) fn insert_emitter_icon(
+    entity_commands: &mut bevy::ecs::system::EntityCommands,
+    transform: Transform,
+    resources: &Resources,
+) {
+    entity_commands.insert((
+        PbrBundle {
+            mesh: resources.emitter_icon_mesh.clone().unwrap_or_default(),
+            material: resources.emitter_icon_material.clone().unwrap_or_default(),
+            transform,
+            ..default()
+        },
+        HIGHLIGHT_TINT.clone(),
+        NotShadowCaster,
+    ));
+}
+
+// Emitter objects follow their `Object`, which is changed by the right panel.
+pub(crate) fn process_particles(
+    mut commands: Commands,
+    object_query: Query<(Entity, &Object), Changed<Object>>,
+    children_query: Query<&Children>,
+    effect_query: Query<(), With<ParticleEmitterEffect>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+) {
+    for (entity, object) in object_query.iter() {
+        if object.object_type != ObjectType::Particles {
+            continue;
+        }
+
+        let particles = crate::if_none_continue!(object.particles.as_ref());
+
+        log::info!("process_particles {:?}", entity);
+
+        if let Ok(children) = children_query.get(entity) {
+            for child in children.iter().filter(|child| effect_query.contains(**child)) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+
+        spawn_particle_effect(&mut commands, entity, particles, &mut effects);
+    }
+}
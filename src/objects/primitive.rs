@@ -0,0 +1,203 @@
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::{log, prelude::*};
+use ::serde::{Deserialize, Serialize};
+
+use super::collider::{add_collider_from_mesh, aply_collider_data, FROM_FLOAT, TO_FLOAT};
+use super::spawn::Resources;
+use super::{Collider, Object, ObjectType};
+
+// Sizes are fixed point like `ColliderType`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum PrimitiveShape {
+    Cube((u32, u32, u32)), // size x, y, z
+
+    Sphere(u32), // radius
+
+    Plane((u32, u32)), // size x, z
+
+    Cylinder((u32, u32)), // height, radius
+
+    Capsule((u32, u32)), // height of the cylinder part, radius
+
+    Torus((u32, u32)), // radius, ring radius
+}
+
+impl Default for PrimitiveShape {
+    fn default() -> Self {
+        PrimitiveShape::Cube((TO_FLOAT as u32, TO_FLOAT as u32, TO_FLOAT as u32))
+    }
+}
+
+impl ToString for PrimitiveShape {
+    fn to_string(&self) -> String {
+        return match self {
+            PrimitiveShape::Cube(_) => "cube".to_string(),
+            PrimitiveShape::Sphere(_) => "sphere".to_string(),
+            PrimitiveShape::Plane(_) => "plane".to_string(),
+            PrimitiveShape::Cylinder(_) => "cylinder".to_string(),
+            PrimitiveShape::Capsule(_) => "capsule".to_string(),
+            PrimitiveShape::Torus(_) => "torus".to_string(),
+        };
+    }
+}
+
+impl PrimitiveShape {
+    // Every shape with its default size, in the order of the palette.
+    pub fn all() -> Vec<PrimitiveShape> {
+        let one = TO_FLOAT as u32;
+        let half = one / 2;
+
+        vec![
+            PrimitiveShape::Cube((one, one, one)),
+            PrimitiveShape::Sphere(half),
+            PrimitiveShape::Plane((one * 10, one * 10)),
+            PrimitiveShape::Cylinder((one, half)),
+            PrimitiveShape::Capsule((one, half)),
+            PrimitiveShape::Torus((one, one / 4)),
+        ]
+    }
+
+    pub fn to_mesh(&self) -> Mesh {
+        match *self {
+            PrimitiveShape::Cube((x, y, z)) => Mesh::from(shape::Box::new(
+                x as f32 * FROM_FLOAT,
+                y as f32 * FROM_FLOAT,
+                z as f32 * FROM_FLOAT,
+            )),
+            PrimitiveShape::Sphere(radius) => Mesh::from(shape::UVSphere {
+                radius: radius as f32 * FROM_FLOAT,
+                ..default()
+            }),
+            PrimitiveShape::Plane((x, z)) => {
+                let mut mesh = Mesh::from(shape::Plane { size: 1.0, subdivisions: 0 });
+
+                if let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION) {
+                    for position in positions.iter_mut() {
+                        position[0] *= x as f32 * FROM_FLOAT;
+                        position[2] *= z as f32 * FROM_FLOAT;
+                    }
+                }
+
+                mesh
+            },
+            PrimitiveShape::Cylinder((height, radius)) => Mesh::from(shape::Cylinder {
+                height: height as f32 * FROM_FLOAT,
+                radius: radius as f32 * FROM_FLOAT,
+                ..default()
+            }),
+            PrimitiveShape::Capsule((height, radius)) => Mesh::from(shape::Capsule {
+                depth: height as f32 * FROM_FLOAT,
+                radius: radius as f32 * FROM_FLOAT,
+                ..default()
+            }),
+            PrimitiveShape::Torus((radius, ring_radius)) => Mesh::from(shape::Torus {
+                radius: radius as f32 * FROM_FLOAT,
+                ring_radius: ring_radius as f32 * FROM_FLOAT,
+                ..default()
+            }),
+        }
+    }
+
+    // Planes and tori have no matching rapier shape and use the trimesh.
+    pub fn to_analytic_collider(&self) -> Option<bevy_rapier3d::prelude::Collider> {
+        match *self {
+            PrimitiveShape::Cube((x, y, z)) => Some(bevy_rapier3d::prelude::Collider::cuboid(
+                x as f32 * FROM_FLOAT * 0.5,
+                y as f32 * FROM_FLOAT * 0.5,
+                z as f32 * FROM_FLOAT * 0.5,
+            )),
+            PrimitiveShape::Sphere(radius) => Some(bevy_rapier3d::prelude::Collider::ball(
+                radius as f32 * FROM_FLOAT,
+            )),
+            PrimitiveShape::Cylinder((height, radius)) => Some(bevy_rapier3d::prelude::Collider::cylinder(
+                height as f32 * FROM_FLOAT * 0.5,
+                radius as f32 * FROM_FLOAT,
+            )),
+            PrimitiveShape::Capsule((height, radius)) => Some(bevy_rapier3d::prelude::Collider::capsule_y(
+                height as f32 * FROM_FLOAT * 0.5,
+                radius as f32 * FROM_FLOAT,
+            )),
+            PrimitiveShape::Plane(_) | PrimitiveShape::Torus(_) => None,
+        }
+    }
+}
+
+// The collider of a primitive object is built when `Object::collider` is set, only its
+// `collider_data` is used.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct Primitive {
+    pub shape: PrimitiveShape,
+    pub analytic_collider: bool,
+}
+
+impl Primitive {
+    pub fn new(shape: PrimitiveShape) -> Self {
+        Self {
+            shape,
+            ..Default::default()
+        }
+    }
+}
+
+// Replaces the mesh and the collider of a primitive object, the material is left to the caller.
+pub fn insert_primitive(
+    commands: &mut Commands,
+    entity: Entity,
+    primitive: &Primitive,
+    collider: Option<&Collider>,
+    meshes: &mut Assets<Mesh>,
+) {
+    let mesh = primitive.shape.to_mesh();
+
+    let mut entity_commands = crate::if_none_return!(commands.get_entity(entity));
+
+    entity_commands.remove::<(
+        bevy_rapier3d::prelude::Collider,
+        bevy_rapier3d::prelude::Friction,
+        bevy_rapier3d::prelude::RigidBody,
+    )>();
+
+    if let Some(collider) = collider {
+        match primitive.shape.to_analytic_collider().filter(|_| primitive.analytic_collider) {
+            Some(analytic_collider) => {
+                entity_commands.insert(analytic_collider);
+                aply_collider_data(commands, &entity, &collider.collider_data);
+            },
+            None => add_collider_from_mesh(commands, &entity, &mesh, &collider.collider_data),
+        };
+    }
+
+    commands.entity(entity).insert(meshes.add(mesh));
+}
+
+// Primitive objects follow their `Object`, which is changed by the right panel.
+pub(crate) fn process_primitives(
+    mut commands: Commands,
+    object_query: Query<(Entity, &Object), Changed<Object>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (entity, object) in object_query.iter() {
+        if object.object_type != ObjectType::Primitive {
+            continue;
+        }
+
+        let primitive = crate::if_none_continue!(object.primitive.as_ref());
+
+        log::info!("process_primitives {}", primitive.shape.to_string());
+
+        insert_primitive(&mut commands, entity, primitive, object.collider.as_ref(), &mut meshes);
+    }
+}
+
+// The mesh is added by `process_primitives`.
+pub(crate) fn insert_primitive_bundle(
+    entity_commands: &mut bevy::ecs::system::EntityCommands,
+    transform: Transform,
+    resources: &Resources,
+) {
+    entity_commands.insert(PbrBundle {
+        material: resources.primitive_material.clone().unwrap_or_default(),
+        transform,
+        ..default()
+    });
+}
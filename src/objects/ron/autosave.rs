@@ -0,0 +1,302 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bevy::{log, prelude::*};
+use ::serde::{Serialize, Deserialize};
+
+use crate::editor::ClearLevelEvent;
+use crate::gui::FileState;
+use crate::objects::Object;
+use crate::{if_err_return, if_none_return};
+
+use super::{LoadRonEvent, SaveRonEvent};
+use super::layers::LevelLayers;
+
+const RECOVERY_DIR: &str = ".recovery";
+const SESSION_FILE: &str = "session.ron";
+const UNTITLED: &str = "untitled";
+
+#[derive(Resource, Clone)]
+pub struct AutosaveSettings {
+    pub enabled: bool,
+    pub interval: Duration,
+    // Snapshots kept for every level.
+    pub keep: usize,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_secs(120),
+            keep: 5,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct AutosaveState {
+    elapsed: Duration,
+    // Level file of the snapshot being saved, `None` for an unsaved level.
+    file: Option<PathBuf>,
+    // Snapshot restored on startup and the level file it belongs to.
+    restoring: Option<(PathBuf, Option<PathBuf>)>,
+}
+
+// The newest snapshot, always kept in the recovery dir of the assets folder
+// so that it can be found on startup.
+#[derive(Serialize, Deserialize)]
+struct RecoverySession {
+    file: Option<PathBuf>,
+    snapshot: PathBuf,
+}
+
+// Snapshots go through the usual save pipeline. Clearing the level is requested
+// by the gui, so a snapshot is taken in the same frame, before the objects are despawned.
+pub(crate) fn process_autosave(
+    time: Res<Time>,
+    settings: Res<AutosaveSettings>,
+    mut state: ResMut<AutosaveState>,
+    file_state: Res<FileState>,
+    object_query: Query<(), With<Object>>,
+    mut clear_reader: EventReader<ClearLevelEvent>,
+    mut writer: EventWriter<SaveRonEvent>,
+) {
+    let cleared = !clear_reader.is_empty();
+    clear_reader.clear();
+
+    state.elapsed += time.delta();
+
+    if !settings.enabled || (!cleared && state.elapsed < settings.interval) {
+        return;
+    }
+
+    if file_state.qnt_loading_ogjects != 0 || file_state.load_handle.is_some() || object_query.is_empty() {
+        return;
+    }
+
+    state.elapsed = Duration::ZERO;
+    state.file = file_state.current_file_path.clone();
+
+    let path = recovery_dir(&file_state).join(snapshot_name(&state.file, timestamp()));
+
+    log::info!("process_autosave {:?}", path);
+
+    writer.send(SaveRonEvent {
+        path: Some(path),
+        root: None,
+        snapshot: true,
+    });
+}
+
+pub(crate) fn write_snapshot(world: &World, path: &Path, serialized: &str) {
+    let file_state = world.resource::<FileState>();
+    let state = world.resource::<AutosaveState>();
+    let session_path = session_path(&file_state.assets_path);
+
+    // Nothing changed since the last snapshot.
+    if let Some(session) = read_session(&session_path) {
+        if session.file == state.file
+            && fs::read_to_string(&session.snapshot).map_or(false, |last| last == serialized)
+        {
+            return;
+        }
+    }
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, serialized));
+
+    if let Err(e) = result {
+        log::error!("Failed to write snapshot {}: {}", path.display(), e);
+        return;
+    }
+
+    let session = RecoverySession {
+        file: state.file.clone(),
+        snapshot: path.to_path_buf(),
+    };
+
+    let result = ron::ser::to_string_pretty(&session, super::sawer::pretty_config())
+        .map_err(|e| e.to_string())
+        .and_then(|serialized| {
+            session_path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(&session_path, serialized))
+                .map_err(|e| e.to_string())
+        });
+
+    if let Err(e) = result {
+        log::error!("Failed to write recovery session {}: {}", session_path.display(), e);
+    }
+
+    remove_old_snapshots(path, &snapshot_stem(&state.file), world.resource::<AutosaveSettings>().keep);
+}
+
+pub(crate) fn check_recovery(
+    file_state: Res<FileState>,
+    mut state: ResMut<AutosaveState>,
+    mut load_writer: EventWriter<LoadRonEvent>,
+) {
+    let session_path = session_path(&file_state.assets_path);
+    let session = if_none_return!(read_session(&session_path));
+
+    let snapshot_time = if_err_return!(fs::metadata(&session.snapshot).and_then(|m| m.modified()));
+
+    if let Some(file) = &session.file {
+        if let Ok(file_time) = fs::metadata(file).and_then(|m| m.modified()) {
+            if file_time >= snapshot_time {
+                return;
+            }
+        }
+    }
+
+    let name = match &session.file {
+        Some(file) => file.display().to_string(),
+        None => "an unsaved level".to_string(),
+    };
+
+    if rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Restore level")
+        .set_description(&format!("An autosaved snapshot of {} is newer than the saved file.\nDo you want to restore it?", name))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+    {
+        log::info!("check_recovery: restore {:?}", session.snapshot);
+
+        state.restoring = Some((session.snapshot.clone(), session.file));
+        load_writer.send(LoadRonEvent { path: Some(session.snapshot) });
+    } else if let Err(e) = fs::remove_file(&session_path) {
+        log::error!("Failed to remove recovery session {}: {}", session_path.display(), e);
+    }
+}
+
+// The restored level is saved to its own file, not over the snapshot.
+pub(crate) fn process_restored_path(
+    mut state: ResMut<AutosaveState>,
+    mut file_state: ResMut<FileState>,
+    mut layers: ResMut<LevelLayers>,
+) {
+    let (snapshot, file) = if_none_return!(state.restoring.clone());
+
+    if file_state.current_file_path.as_ref() == Some(&snapshot) {
+        // The layer paths of the snapshot belong to the restored level.
+        layers.level = file.clone();
+        file_state.current_file_path = file;
+        state.restoring = None;
+    }
+}
+
+fn recovery_dir(file_state: &FileState) -> PathBuf {
+    match file_state.current_file_path.as_ref().and_then(|path| path.parent()) {
+        Some(dir) => dir.join(RECOVERY_DIR),
+        None => file_state.assets_path.join(RECOVERY_DIR),
+    }
+}
+
+fn session_path(assets_path: &Path) -> PathBuf {
+    assets_path.join(RECOVERY_DIR).join(SESSION_FILE)
+}
+
+fn read_session(path: &Path) -> Option<RecoverySession> {
+    let serialized = fs::read_to_string(path).ok()?;
+
+    ron::de::from_str(&serialized)
+        .map_err(|e| log::error!("Failed to read recovery session {}: {}", path.display(), e))
+        .ok()
+}
+
+fn snapshot_stem(file: &Option<PathBuf>) -> String {
+    file.as_ref()
+        .and_then(|file| file.file_stem())
+        .map_or(UNTITLED.to_string(), |stem| stem.to_string_lossy().to_string())
+}
+
+fn snapshot_name(file: &Option<PathBuf>, timestamp: u128) -> String {
+    format!("{}.{}.ron", snapshot_stem(file), timestamp)
+}
+
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis())
+}
+
+// Snapshots are named `<level>.<timestamp>.ron`, only the newest `keep` of them are left.
+fn remove_old_snapshots(path: &Path, stem: &str, keep: usize) {
+    let dir = if_none_return!(path.parent());
+    let entries = if_err_return!(fs::read_dir(dir));
+
+    let mut snapshots: Vec<(u128, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let timestamp = name
+                .strip_prefix(stem)?
+                .strip_prefix('.')?
+                .strip_suffix(".ron")?
+                .parse()
+                .ok()?;
+
+            Some((timestamp, entry.path()))
+        })
+        .collect();
+
+    snapshots.sort_by(|a, b| b.0.cmp(&a.0));
+
+    for (_, path) in snapshots.into_iter().skip(keep.max(1)) {
+        if let Err(e) = fs::remove_file(&path) {
+            log::error!("Failed to remove snapshot {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_names() {
+        assert_eq!(snapshot_name(&Some(PathBuf::from("levels/castle.ron")), 1700000000123), "castle.1700000000123.ron");
+        assert_eq!(snapshot_name(&None, 42), "untitled.42.ron");
+    }
+
+    #[test]
+    fn old_snapshots_of_the_level_are_removed() {
+        let dir = std::env::temp_dir().join(format!("kns_snapshots_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let castle = Some(PathBuf::from("castle.ron"));
+        // Starts with the name of the castle level, but is another level.
+        let castle_2 = Some(PathBuf::from("castle.2.ron"));
+
+        for timestamp in 1..=7 {
+            for file in [&castle, &castle_2, &None] {
+                fs::write(dir.join(snapshot_name(file, timestamp)), "").unwrap();
+            }
+        }
+
+        fs::write(dir.join("castle.ron"), "").unwrap();
+
+        remove_old_snapshots(&dir.join(snapshot_name(&castle, 7)), "castle", 5);
+
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut expected: Vec<String> = (3..=7).map(|timestamp| snapshot_name(&castle, timestamp)).collect();
+        expected.extend((1..=7).map(|timestamp| snapshot_name(&castle_2, timestamp)));
+        expected.extend((1..=7).map(|timestamp| snapshot_name(&None, timestamp)));
+        expected.push("castle.ron".to_string());
+        expected.sort();
+
+        assert_eq!(names, expected);
+    }
+}
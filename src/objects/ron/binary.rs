@@ -0,0 +1,304 @@
+use std::path::Path;
+use ::serde::Deserialize;
+
+use super::Ron;
+use super::migration::{RonError, RON_VERSION};
+use super::sawer::serialize_ron;
+
+// Compact encoding of the same `Ron` level for large levels: a magic header and MessagePack
+// with named fields. Binary levels are read with the current layout, so changes of the
+// level format must keep old fields readable with serde defaults.
+pub const BINARY_EXTENSION: &str = "ronb";
+
+const MAGIC: &[u8; 4] = b"KNSL";
+
+// Read before the level, so that newer files are rejected by their version even if
+// their layout doesn't decode any more. Other fields are ignored.
+#[derive(Deserialize)]
+struct BinaryHeader {
+    version: u32,
+}
+
+impl From<rmp_serde::encode::Error> for RonError {
+    fn from(e: rmp_serde::encode::Error) -> Self {
+        RonError::Parse(e.to_string())
+    }
+}
+
+impl From<rmp_serde::decode::Error> for RonError {
+    fn from(e: rmp_serde::decode::Error) -> Self {
+        RonError::Parse(e.to_string())
+    }
+}
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+pub fn is_binary_path(path: &Path) -> bool {
+    path.extension().map_or(false, |extension| extension == BINARY_EXTENSION)
+}
+
+pub fn serialize_binary(ron: &Ron) -> Result<Vec<u8>, RonError> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.append(&mut rmp_serde::to_vec_named(ron)?);
+
+    Ok(bytes)
+}
+
+pub fn deserialize_binary(bytes: &[u8]) -> Result<Ron, RonError> {
+    let bytes = bytes
+        .strip_prefix(MAGIC)
+        .ok_or(RonError::Parse("not a binary level".to_string()))?;
+
+    let header: BinaryHeader = rmp_serde::from_slice(bytes)?;
+
+    if header.version > RON_VERSION {
+        return Err(RonError::UnsupportedVersion { found: header.version, supported: RON_VERSION });
+    }
+
+    Ok(rmp_serde::from_slice(bytes)?)
+}
+
+// Text or binary, depending on the extension of the level file.
+pub fn serialize_level(ron: &Ron, path: &Path) -> Result<Vec<u8>, RonError> {
+    if is_binary_path(path) {
+        serialize_binary(ron)
+    } else {
+        serialize_ron(ron).map(String::into_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::objects::{Collider, ColliderData, ColliderType, EditorId, LightData, LightType, Object, ObjectType, Primitive, PrimitiveShape, MaterialOverride, MaterialAlphaMode, ParticleData};
+    use crate::objects::ron::{LevelSettings, RonComponent, RonComponentValue, RonLayer, RonLoader, RonNode, RonOverride, RonTransform};
+    use super::*;
+
+    fn level() -> Ron {
+        let ids: Vec<EditorId> = (0..6).map(|_| EditorId::default()).collect();
+
+        let mut ron = Ron {
+            version: RON_VERSION,
+            objects: vec![
+                Object {
+                    object_type: ObjectType::Scene,
+                    path: Some(PathBuf::from("gltf/ground_4/scene.gltf")),
+                    collider: Some(Collider {
+                        collider_type: ColliderType::FromBevyMesh,
+                        collider_data: ColliderData::new(0.3, true, 1, 2, 3, 4),
+                    }),
+                    label: None,
+                    light: None,
+                    primitive: None,
+                    material: None,
+                    particles: None,
+                },
+                Object {
+                    object_type: ObjectType::Mesh,
+                    path: Some(PathBuf::from("props://barrel.gltf")),
+                    collider: None,
+                    label: Some("Mesh2".to_string()),
+                    light: None,
+                    primitive: None,
+                    material: None,
+                    particles: None,
+                },
+                Object {
+                    object_type: ObjectType::Collider,
+                    path: None,
+                    collider: Some(Collider {
+                        collider_type: ColliderType::Cuboid((10000, 5000, 2500)),
+                        collider_data: ColliderData::default(),
+                    }),
+                    label: None,
+                    light: None,
+                    primitive: None,
+                    material: None,
+                    particles: None,
+                },
+                Object {
+                    object_type: ObjectType::Light,
+                    path: None,
+                    collider: None,
+                    label: None,
+                    light: Some(LightData {
+                        shadows_enabled: true,
+                        ..LightData::new(LightType::Spot)
+                    }),
+                    primitive: None,
+                    material: None,
+                    particles: None,
+                },
+                Object {
+                    object_type: ObjectType::Primitive,
+                    path: None,
+                    collider: Some(Collider::default()),
+                    label: None,
+                    light: None,
+                    primitive: Some(Primitive {
+                        shape: PrimitiveShape::Capsule((20000, 5000)),
+                        analytic_collider: true,
+                    }),
+                    material: Some(MaterialOverride {
+                        base_color_texture: Some(PathBuf::from("textures/rock.png")),
+                        alpha_mode: MaterialAlphaMode::Mask(5000),
+                        ..Default::default()
+                    }),
+                    particles: None,
+                },
+                Object {
+                    object_type: ObjectType::Particles,
+                    path: None,
+                    collider: None,
+                    label: None,
+                    light: None,
+                    primitive: None,
+                    material: None,
+                    particles: Some(ParticleData {
+                        acceleration: [0, -98100, 2500],
+                        ..Default::default()
+                    }),
+                },
+            ],
+            nodes: Vec::new(),
+            layers: vec![RonLayer {
+                name: "props".to_string(),
+                path: PathBuf::from("level.props.ron"),
+                visible: true,
+                locked: false,
+            }],
+            settings: Some(LevelSettings {
+                author: "kns".to_string(),
+                description: "A test level".to_string(),
+                gravity: [0., -3.7, 0.],
+                ..Default::default()
+            }),
+            snapshot_layers: None,
+        };
+
+        for (index, id) in ids.iter().enumerate() {
+            ron.nodes.push(RonNode {
+                id: *id,
+                transform: RonTransform {
+                    translation: [index as f32 * 1.5, -0.1, 1e-7],
+                    rotation: [0., 0.38268343, 0., 0.9238795],
+                    scale: [1., 2., 0.333],
+                },
+                object: index,
+                has_parent: index > 0,
+                childrens: if index == 0 { ids[1..].to_vec() } else { Vec::new() },
+                components: vec![RonComponent {
+                    type_name: "game::Health".to_string(),
+                    value: RonComponentValue(serde_json::json!({ "value": 100 })),
+                }],
+                overrides: if index == 0 {
+                    vec![RonOverride {
+                        node: EditorId::default(),
+                        transform: Some(RonTransform::default()),
+                        object: None,
+                        components: Some(Vec::new()),
+                    }]
+                } else {
+                    Vec::new()
+                },
+                layer: None,
+            });
+        }
+
+        ron.sort();
+        ron
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let ron = level();
+        let bytes = serialize_binary(&ron).unwrap();
+
+        assert!(is_binary(&bytes));
+        assert_eq!(deserialize_binary(&bytes).unwrap(), ron);
+    }
+
+    #[test]
+    fn binary_matches_text() {
+        let ron = level();
+        let loader = RonLoader::default();
+
+        let text = serialize_level(&ron, Path::new("level.ron")).unwrap();
+        let binary = serialize_level(&ron, Path::new("level.ronb")).unwrap();
+
+        assert!(!is_binary(&text));
+        assert!(binary.len() < text.len());
+
+        let from_text = loader.parse_bytes(&text).unwrap();
+        let from_binary = loader.parse_bytes(&binary).unwrap();
+
+        assert_eq!(from_text, ron);
+        assert_eq!(from_binary, from_text);
+        assert_eq!(serialize_ron(&from_binary).unwrap().into_bytes(), text);
+    }
+
+    #[test]
+    fn layer_levels_have_no_settings() {
+        let mut ron = level();
+        ron.layers.clear();
+        ron.settings = None;
+
+        let text = serialize_ron(&ron).unwrap();
+
+        assert!(!text.contains("settings"));
+        assert!(!text.contains("layers"));
+
+        let loader = RonLoader::default();
+
+        assert_eq!(loader.parse(&text).unwrap(), ron);
+        assert_eq!(deserialize_binary(&serialize_binary(&ron).unwrap()).unwrap(), ron);
+
+        let master = serialize_ron(&level()).unwrap();
+
+        assert!(master.contains("settings: ("));
+    }
+
+    #[test]
+    fn binary_rejects_newer_version() {
+        let mut ron = level();
+        ron.version = RON_VERSION + 1;
+
+        let bytes = serialize_binary(&ron).unwrap();
+
+        assert!(matches!(
+            deserialize_binary(&bytes),
+            Err(RonError::UnsupportedVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn binary_rejects_newer_layout_by_version() {
+        #[derive(::serde::Serialize)]
+        struct Future {
+            nodes: u64,
+            version: u32,
+            objects: String,
+        }
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.append(&mut rmp_serde::to_vec_named(&Future {
+            nodes: 3,
+            version: RON_VERSION + 1,
+            objects: "objects".to_string(),
+        }).unwrap());
+
+        assert!(matches!(
+            deserialize_binary(&bytes),
+            Err(RonError::UnsupportedVersion { found, .. }) if found == RON_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn binary_rejects_text() {
+        let text = serialize_ron(&level()).unwrap();
+
+        assert!(deserialize_binary(text.as_bytes()).is_err());
+    }
+}
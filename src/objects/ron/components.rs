@@ -0,0 +1,269 @@
+use std::any::TypeId;
+use std::hash::{Hash, Hasher};
+use ::serde::{Serialize, Deserialize, de::DeserializeSeed};
+use bevy::ecs::system::Command;
+use bevy::prelude::*;
+use bevy::reflect::{GetTypeRegistration, TypeRegistration, TypeRegistryInternal};
+use bevy::reflect::serde::{TypedReflectSerializer, TypedReflectDeserializer};
+use bevy::utils::HashSet;
+use bevy::log;
+
+// A reflected component stored on a level node.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct RonComponent {
+    pub type_name: String,
+    pub value: RonComponentValue,
+}
+
+// The component written inline by `TypedReflectSerializer`. Not a `ron::Value`, which drops
+// the names of enum variants. Levels before version 15 have the ron text of the component.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Reflect, FromReflect, Serialize, Deserialize)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RonComponentValue(pub serde_json::Value);
+
+impl Hash for RonComponentValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_string().hash(state);
+    }
+}
+
+// Components that are saved with level nodes. Types must also be registered in `AppTypeRegistry`
+// with `#[reflect(Component)]`.
+#[derive(Default, Debug, Resource)]
+pub struct SavedComponents {
+    types: HashSet<TypeId>,
+}
+
+impl SavedComponents {
+    pub fn allow<T: Component + GetTypeRegistration>(&mut self) -> &mut Self {
+        self.types.insert(TypeId::of::<T>());
+        self
+    }
+
+    pub fn contains(&self, type_id: TypeId) -> bool {
+        self.types.contains(&type_id)
+    }
+}
+
+// Copies of the allowed components of the entity, sorted by type name.
+pub(crate) fn reflect_components(
+    world: &World,
+    entity: Entity,
+) -> Vec<Box<dyn Reflect>> {
+    let mut components = Vec::new();
+
+    let saved = world.resource::<SavedComponents>();
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let entity_ref = match world.get_entity(entity) {
+        Some(entity_ref) => entity_ref,
+        None => return components,
+    };
+
+    for type_id in saved.types.iter() {
+        let registration = crate::if_none_continue!(registry.get(*type_id));
+        let reflect_component = crate::if_none_continue!(registration.data::<ReflectComponent>());
+        let component = crate::if_none_continue!(reflect_component.reflect(entity_ref));
+
+        components.push(component.clone_value());
+    }
+
+    components.sort_by(|a, b| a.type_name().cmp(b.type_name()));
+
+    components
+}
+
+// The allowed components of the entity as saved on level nodes.
+pub(crate) fn collect_components(
+    world: &World,
+    entity: Entity,
+) -> Vec<RonComponent> {
+    let components = reflect_components(world, entity);
+    let registry = world.resource::<AppTypeRegistry>().read();
+
+    components
+        .iter()
+        .filter_map(|component| match serde_json::to_value(TypedReflectSerializer::new(&**component, &registry)) {
+            Ok(value) => Some(RonComponent {
+                type_name: component.type_name().to_string(),
+                value: RonComponentValue(value),
+            }),
+            Err(e) => {
+                log::error!("collect_components: failed to serialize {}: {}", component.type_name(), e);
+                None
+            },
+        })
+        .collect()
+}
+
+pub(crate) struct InsertRonComponents {
+    pub entity: Entity,
+    pub components: Vec<RonComponent>,
+}
+
+impl Command for InsertRonComponents {
+    fn write(self, world: &mut World) {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+
+        for component in self.components.iter() {
+            match deserialize_component(&registry, component) {
+                Ok(value) => insert_component(world, &registry, self.entity, &*value),
+                Err(e) => log::error!("InsertRonComponents: {}", e),
+            };
+        }
+    }
+}
+
+pub(crate) struct InsertReflectComponents {
+    pub entity: Entity,
+    pub components: Vec<Box<dyn Reflect>>,
+}
+
+impl Command for InsertReflectComponents {
+    fn write(self, world: &mut World) {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+
+        for component in self.components.iter() {
+            insert_component(world, &registry, self.entity, &**component);
+        }
+    }
+}
+
+// Only components allowed in `SavedComponents` are inserted.
+fn insert_component(
+    world: &mut World,
+    registry: &TypeRegistryInternal,
+    entity: Entity,
+    value: &dyn Reflect,
+) {
+    let registration = match registry.get_with_name(value.type_name()) {
+        Some(registration) => registration,
+        None => {
+            log::error!("insert_component: {} is not registered", value.type_name());
+            return;
+        }
+    };
+
+    if !world.resource::<SavedComponents>().contains(registration.type_id()) {
+        log::warn!("insert_component: {} is not a saved component", value.type_name());
+        return;
+    }
+
+    let reflect_component = crate::if_none_return!(registration.data::<ReflectComponent>());
+    let mut entity_mut = crate::if_none_return!(world.get_entity_mut(entity));
+
+    reflect_component.insert(&mut entity_mut, value);
+}
+
+fn deserialize_component(
+    registry: &TypeRegistryInternal,
+    component: &RonComponent,
+) -> Result<Box<dyn Reflect>, String> {
+    let registration = registry
+        .get_with_name(&component.type_name)
+        .ok_or(format!("{} is not registered", component.type_name))?;
+
+    let value = TypedReflectDeserializer::new(registration, registry)
+        .deserialize(&component.value.0)
+        .map_err(|e| format!("{}: {}", component.type_name, e));
+
+    match (value, &component.value.0) {
+        (Err(_), serde_json::Value::String(text)) => deserialize_ron_text(registry, registration, &component.type_name, text),
+        (value, _) => value,
+    }
+}
+
+// Components of levels saved before the values were inline.
+fn deserialize_ron_text(
+    registry: &TypeRegistryInternal,
+    registration: &TypeRegistration,
+    type_name: &str,
+    text: &str,
+) -> Result<Box<dyn Reflect>, String> {
+    let mut deserializer = ron::de::Deserializer::from_str(text)
+        .map_err(|e| format!("{}: {}", type_name, e))?;
+
+    TypedReflectDeserializer::new(registration, registry)
+        .deserialize(&mut deserializer)
+        .map_err(|e| format!("{}: {}", type_name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+    use bevy::prelude::*;
+    use super::*;
+
+    #[derive(Default, Debug, Clone, PartialEq, Reflect, FromReflect)]
+    enum Team {
+        #[default]
+        Red,
+        Blue(u32),
+    }
+
+    #[derive(Component, Default, Debug, Clone, PartialEq, Reflect, FromReflect)]
+    #[reflect(Component)]
+    struct Player {
+        team: Team,
+        health: Option<u32>,
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+
+        let registry = AppTypeRegistry::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<Team>();
+            registry.register::<Player>();
+            registry.register::<Option<u32>>();
+        }
+        world.insert_resource(registry);
+
+        let mut saved = SavedComponents::default();
+        saved.allow::<Player>();
+        world.insert_resource(saved);
+
+        world
+    }
+
+    #[test]
+    fn components_round_trip_inline() {
+        let mut world = world();
+        let player = Player { team: Team::Blue(3), health: Some(5) };
+        let entity = world.spawn(player.clone()).id();
+
+        let components = collect_components(&world, entity);
+
+        assert_eq!(components.len(), 1);
+
+        let text = ron::ser::to_string(&components).unwrap();
+        let components: Vec<RonComponent> = ron::de::from_str(&text).unwrap();
+
+        assert!(text.contains("Blue"));
+
+        let loaded = world.spawn_empty().id();
+        InsertRonComponents { entity: loaded, components }.write(&mut world);
+
+        assert_eq!(world.get::<Player>(loaded), Some(&player));
+    }
+
+    #[test]
+    fn legacy_ron_text_is_read() {
+        let mut world = world();
+        let loaded = world.spawn_empty().id();
+
+        InsertRonComponents {
+            entity: loaded,
+            components: vec![RonComponent {
+                type_name: std::any::type_name::<Player>().to_string(),
+                value: RonComponentValue(serde_json::Value::String("(team: Blue(3), health: Some(5))".to_string())),
+            }],
+        }
+        .write(&mut world);
+
+        assert_eq!(world.get::<Player>(loaded), Some(&Player { team: Team::Blue(3), health: Some(5) }));
+    }
+}
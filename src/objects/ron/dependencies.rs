@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+// Files referenced by the loaded ron levels, keyed by asset path.
+#[derive(Resource, Default, Debug)]
+pub struct RonDependencies {
+    dependencies: HashMap<PathBuf, Vec<PathBuf>>,
+    cycles: Vec<Vec<PathBuf>>,
+}
+
+impl RonDependencies {
+    pub fn clear(&mut self) {
+        self.dependencies.clear();
+        self.cycles.clear();
+    }
+
+    pub fn insert(&mut self, path: &Path, dependencies: Vec<PathBuf>) {
+        self.dependencies.insert(path.to_path_buf(), dependencies);
+    }
+
+    // The chain `path -> ron -> .. -> path` if the nested ron leads back to the level.
+    pub fn find_cycle(&self, path: &Path, ron: &Path) -> Option<Vec<PathBuf>> {
+        let mut chain = self.find_chain(ron, path)?;
+        chain.insert(0, path.to_path_buf());
+
+        Some(chain)
+    }
+
+    // Returns false if the cycle is already known.
+    pub fn add_cycle(&mut self, chain: Vec<PathBuf>) -> bool {
+        if self.cycles.contains(&chain) {
+            return false;
+        }
+
+        self.cycles.push(chain);
+        true
+    }
+
+    pub fn cycles(&self) -> &[Vec<PathBuf>] {
+        &self.cycles
+    }
+
+    pub fn is_cyclic(&self, path: &Path) -> bool {
+        self.cycles.iter().any(|chain| chain.iter().any(|p| p == path))
+    }
+
+    pub fn files(&self) -> Vec<&PathBuf> {
+        let mut files: Vec<&PathBuf> = self.dependencies.keys().collect();
+        files.sort();
+        files
+    }
+
+    pub fn direct_dependencies(&self, path: &Path) -> &[PathBuf] {
+        self.dependencies.get(path).map_or(&[], |dependencies| dependencies.as_slice())
+    }
+
+    // All files needed to load the level, sorted.
+    pub fn dependencies(&self, path: &Path) -> Vec<PathBuf> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![path.to_path_buf()];
+
+        while let Some(current) = stack.pop() {
+            for dependency in self.direct_dependencies(&current).iter() {
+                if visited.insert(dependency.clone()) {
+                    stack.push(dependency.clone());
+                }
+            }
+        }
+
+        let mut dependencies: Vec<PathBuf> = visited.into_iter().collect();
+        dependencies.sort();
+        dependencies
+    }
+
+    // Levels that use the file directly or through nested levels, sorted.
+    pub fn users(&self, path: &Path) -> Vec<PathBuf> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![path.to_path_buf()];
+
+        while let Some(current) = stack.pop() {
+            for (user, dependencies) in self.dependencies.iter() {
+                if dependencies.contains(&current) && visited.insert(user.clone()) {
+                    stack.push(user.clone());
+                }
+            }
+        }
+
+        let mut users: Vec<PathBuf> = visited.into_iter().collect();
+        users.sort();
+        users
+    }
+
+    fn find_chain(&self, from: &Path, to: &Path) -> Option<Vec<PathBuf>> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![vec![from.to_path_buf()]];
+
+        while let Some(chain) = stack.pop() {
+            let last = chain.last().expect("find_chain empty chain");
+
+            if last == to {
+                return Some(chain);
+            }
+
+            if !visited.insert(last.clone()) {
+                continue;
+            }
+
+            for dependency in self.direct_dependencies(last).iter() {
+                let mut next = chain.clone();
+                next.push(dependency.clone());
+                stack.push(next);
+            }
+        }
+
+        None
+    }
+}
+
+pub fn format_chain(chain: &[PathBuf]) -> String {
+    chain
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<String>>()
+        .join(" -> ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    fn graph(edges: &[(&str, &[&str])]) -> RonDependencies {
+        let mut dependencies = RonDependencies::default();
+
+        for (path, nested) in edges.iter() {
+            dependencies.insert(Path::new(path), paths(nested));
+        }
+
+        dependencies
+    }
+
+    #[test]
+    fn self_reference_is_a_cycle() {
+        let dependencies = graph(&[("a.ron", &["a.ron"])]);
+
+        assert_eq!(dependencies.find_cycle(Path::new("a.ron"), Path::new("a.ron")), Some(paths(&["a.ron", "a.ron"])));
+        assert_eq!(dependencies.dependencies(Path::new("a.ron")), paths(&["a.ron"]));
+        assert_eq!(dependencies.users(Path::new("a.ron")), paths(&["a.ron"]));
+    }
+
+    #[test]
+    fn transitive_cycle_is_found() {
+        let dependencies = graph(&[
+            ("a.ron", &["b.ron"]),
+            ("b.ron", &["c.ron"]),
+            ("c.ron", &["a.ron"]),
+        ]);
+
+        // `c.ron` nests `a.ron`, which leads back to `c.ron`.
+        let chain = dependencies.find_cycle(Path::new("c.ron"), Path::new("a.ron")).unwrap();
+
+        assert_eq!(chain, paths(&["c.ron", "a.ron", "b.ron", "c.ron"]));
+        assert_eq!(format_chain(&chain), "c.ron -> a.ron -> b.ron -> c.ron");
+
+        assert_eq!(dependencies.dependencies(Path::new("a.ron")), paths(&["a.ron", "b.ron", "c.ron"]));
+        assert_eq!(dependencies.users(Path::new("a.ron")), paths(&["a.ron", "b.ron", "c.ron"]));
+    }
+
+    #[test]
+    fn diamond_is_not_a_cycle() {
+        let dependencies = graph(&[
+            ("a.ron", &["b.ron", "c.ron"]),
+            ("b.ron", &["d.ron"]),
+            ("c.ron", &["d.ron"]),
+            ("d.ron", &[]),
+        ]);
+
+        for (path, nested) in [("a.ron", "b.ron"), ("a.ron", "c.ron"), ("b.ron", "d.ron"), ("c.ron", "d.ron")] {
+            assert_eq!(dependencies.find_cycle(Path::new(path), Path::new(nested)), None);
+        }
+
+        assert_eq!(dependencies.find_chain(Path::new("a.ron"), Path::new("d.ron")).map(|chain| chain.len()), Some(3));
+        assert_eq!(dependencies.dependencies(Path::new("a.ron")), paths(&["b.ron", "c.ron", "d.ron"]));
+        assert_eq!(dependencies.users(Path::new("d.ron")), paths(&["a.ron", "b.ron", "c.ron"]));
+        assert!(dependencies.users(Path::new("a.ron")).is_empty());
+    }
+
+    #[test]
+    fn known_cycles_are_added_once() {
+        let mut dependencies = graph(&[("a.ron", &["a.ron"])]);
+        let chain = paths(&["a.ron", "a.ron"]);
+
+        assert!(dependencies.add_cycle(chain.clone()));
+        assert!(!dependencies.add_cycle(chain));
+        assert!(dependencies.is_cyclic(Path::new("a.ron")));
+        assert!(!dependencies.is_cyclic(Path::new("b.ron")));
+    }
+}
@@ -0,0 +1,377 @@
+use std::path::{Path, PathBuf};
+use bevy::{log, prelude::*};
+use ::serde::{Serialize, Deserialize};
+
+use crate::if_none_continue;
+use crate::objects::{AddObjectEvent, LoadErrorEvent, Object, PrefabNode};
+
+use super::Ron;
+use super::spawn::spawn_roots;
+
+// A named part of the level saved in its own file, `path` is relative to the master level.
+#[derive(Default, Debug, Clone, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct RonLayer {
+    pub name: String,
+    pub path: PathBuf,
+    pub visible: bool,
+    pub locked: bool,
+}
+
+// On the root objects of a layer, the children belong to the layer of their root.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct LevelLayer {
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub struct Layer {
+    pub name: String,
+    // Empty until the level is saved or loaded.
+    pub path: PathBuf,
+    pub visible: bool,
+    pub locked: bool,
+    handle: Option<Handle<Ron>>,
+    // Layers that failed to load are not saved, so their files are kept.
+    loaded: bool,
+}
+
+impl Layer {
+    // `level.layer.ron` for the master level at `level`.
+    fn default_path(&self, level: &Path) -> PathBuf {
+        let stem = level.file_stem().map_or("level".to_string(), |stem| stem.to_string_lossy().to_string());
+        let extension = level.extension().map_or("ron".to_string(), |extension| extension.to_string_lossy().to_string());
+
+        PathBuf::from(format!("{}.{}.{}", stem, file_name(&self.name), extension))
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+}
+
+// The layer name as it is written in file names, compared without case for case insensitive file systems.
+fn file_name(name: &str) -> String {
+    name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct LevelLayers {
+    pub layers: Vec<Layer>,
+    // New root objects are added to the active layer.
+    pub active: Option<String>,
+    // The master level the layer paths belong to.
+    pub level: Option<PathBuf>,
+}
+
+impl LevelLayers {
+    pub fn clear(&mut self) {
+        self.layers.clear();
+        self.active = None;
+        self.level = None;
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn add(&mut self, name: &str) -> bool {
+        if name.is_empty() || self.get(name).is_some() {
+            return false;
+        }
+
+        // Names that only differ in the characters replaced in file names would share a file.
+        let new_file_name = file_name(name).to_lowercase();
+
+        if self.layers.iter().any(|layer| file_name(&layer.name).to_lowercase() == new_file_name) {
+            return false;
+        }
+
+        self.layers.push(Layer {
+            name: name.to_string(),
+            path: PathBuf::new(),
+            visible: true,
+            locked: false,
+            handle: None,
+            loaded: true,
+        });
+
+        true
+    }
+
+    // Objects of a removed layer move to the master level.
+    pub fn remove(&mut self, name: &str) {
+        self.layers.retain(|layer| layer.name != name);
+
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+    }
+
+    pub fn is_locked(&self, layer: Option<&LevelLayer>) -> bool {
+        layer
+            .and_then(|layer| self.get(&layer.name))
+            .map_or(false, |layer| layer.locked)
+    }
+
+    // Relative to the master level at `level`. Saving the level to another file names new layer files
+    // after it, so the layer files of the old level are not overwritten. Layers that failed to load
+    // are not saved and keep their files.
+    pub fn layer_path(&self, layer: &Layer, level: &Path) -> PathBuf {
+        let same_level = self.level.as_deref() == Some(level);
+
+        if !layer.path.as_os_str().is_empty() && (same_level || !layer.loaded) {
+            return layer.path.clone();
+        }
+
+        layer.default_path(level)
+    }
+
+    // Next to the master level at `level`.
+    pub fn file_path(&self, layer: &Layer, level: &Path) -> PathBuf {
+        level.parent().unwrap_or(Path::new("")).join(self.layer_path(layer, level))
+    }
+
+    // After the level was saved at `level`, the layers keep the files they were written to.
+    pub(crate) fn saved(&mut self, level: &Path) {
+        let paths: Vec<PathBuf> = self.layers.iter().map(|layer| self.layer_path(layer, level)).collect();
+
+        for (layer, path) in self.layers.iter_mut().zip(paths) {
+            layer.path = path;
+        }
+
+        self.level = Some(level.to_path_buf());
+    }
+
+    // Layers of the master level at `level`.
+    pub fn to_ron(&self, level: &Path) -> Vec<RonLayer> {
+        self.layers
+            .iter()
+            .map(|layer| RonLayer {
+                name: layer.name.clone(),
+                path: self.layer_path(layer, level),
+                visible: layer.visible,
+                locked: layer.locked,
+            })
+            .collect()
+    }
+
+    // Layers of an autosave snapshot, with the paths as they were.
+    pub fn to_snapshot(&self) -> Vec<RonLayer> {
+        self.layers
+            .iter()
+            .map(|layer| RonLayer {
+                name: layer.name.clone(),
+                path: layer.path.clone(),
+                visible: layer.visible,
+                locked: layer.locked,
+            })
+            .collect()
+    }
+
+    // The objects of the layers are in the snapshot, so no layer file is loaded.
+    // The level the paths belong to is set when the restored level gets its file back.
+    pub(crate) fn restore(&mut self, layers: &[RonLayer]) {
+        let level = self.level.take();
+
+        self.clear();
+        self.level = level;
+
+        for layer in layers.iter() {
+            self.layers.push(Layer {
+                name: layer.name.clone(),
+                path: layer.path.clone(),
+                visible: layer.visible,
+                locked: layer.locked,
+                handle: None,
+                loaded: true,
+            });
+        }
+    }
+
+    pub(crate) fn load(&mut self, layers: &[RonLayer], level: &Path, asset_server: &AssetServer) {
+        self.clear();
+        self.level = Some(level.to_path_buf());
+
+        let directory = level.parent().unwrap_or(Path::new(""));
+
+        for layer in layers.iter() {
+            self.layers.push(Layer {
+                name: layer.name.clone(),
+                path: layer.path.clone(),
+                visible: layer.visible,
+                locked: layer.locked,
+                handle: Some(asset_server.load(directory.join(&layer.path).display().to_string())),
+                loaded: false,
+            });
+        }
+    }
+}
+
+pub struct MoveToLayerEvent {
+    pub entity: Entity,
+    // `None` for the master level.
+    pub layer: Option<String>,
+}
+
+// The layer the object belongs to, through its root object.
+pub fn world_layer<'a>(world: &'a World, entity: Entity) -> Option<&'a LevelLayer> {
+    let mut current = entity;
+
+    while let Some(parent) = world.get::<Parent>(current) {
+        current = parent.get();
+    }
+
+    world.get::<LevelLayer>(current)
+}
+
+pub fn entity_layer<'a>(
+    entity: Entity,
+    parent_query: &Query<&Parent>,
+    layer_query: &'a Query<&LevelLayer>,
+) -> Option<&'a LevelLayer> {
+    let root = parent_query.iter_ancestors(entity).last().unwrap_or(entity);
+
+    layer_query.get(root).ok()
+}
+
+// Layer files are spawned when they are loaded, after the master level.
+pub(crate) fn process_spawn_layers(
+    mut commands: Commands,
+    mut layers: ResMut<LevelLayers>,
+    asset_server: Res<AssetServer>,
+    ron_assets: Res<Assets<Ron>>,
+    mut writer: EventWriter<AddObjectEvent>,
+    mut error_writer: EventWriter<LoadErrorEvent>,
+) {
+    for layer in layers.layers.iter_mut() {
+        let handle = if_none_continue!(layer.handle.clone());
+
+        match asset_server.get_load_state(&handle) {
+            bevy::asset::LoadState::Loaded => (),
+            bevy::asset::LoadState::Failed => {
+                error_writer.send(LoadErrorEvent::new(
+                    Some(layer.path.clone()),
+                    None,
+                    format!("failed to load layer {}", layer.name),
+                ));
+
+                layer.handle = None;
+                continue;
+            },
+            _ => continue,
+        };
+
+        let ron = if_none_continue!(ron_assets.get(&handle));
+
+        log::info!("process_spawn_layers {}", layer.name);
+
+        let file = Some(layer.path.clone());
+
+        for entity in spawn_roots(&mut commands, ron, &file, &mut writer, &mut error_writer) {
+            commands.entity(entity).insert(LevelLayer { name: layer.name.clone() });
+        }
+
+        layer.handle = None;
+        layer.loaded = true;
+    }
+}
+
+// Hidden layers hide their root objects, objects of removed layers move to the master level.
+pub(crate) fn process_layers(
+    mut commands: Commands,
+    layers: Res<LevelLayers>,
+    mut layer_query: Query<(Entity, &LevelLayer, &mut Visibility)>,
+) {
+    for (entity, layer, mut visibility) in layer_query.iter_mut() {
+        let visible = match layers.get(&layer.name) {
+            Some(layer) => layer.visible,
+            None => {
+                commands.entity(entity).remove::<LevelLayer>();
+                true
+            },
+        };
+
+        let wanted = if visible { Visibility::Inherited } else { Visibility::Hidden };
+
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
+
+// The object becomes a root object of the layer, keeping its place in the world.
+pub(crate) fn process_move_to_layer(
+    mut commands: Commands,
+    mut reader: EventReader<MoveToLayerEvent>,
+    layers: Res<LevelLayers>,
+    object_query: Query<(&GlobalTransform, Option<&Parent>, Option<&PrefabNode>), With<Object>>,
+) {
+    for MoveToLayerEvent { entity, layer } in reader.iter() {
+        let (global_transform, parent, prefab_node) = if_none_continue!(object_query.get(*entity).ok());
+
+        if prefab_node.is_some() {
+            log::warn!("process_move_to_layer: nodes of a prefab instance stay in the instance");
+            continue;
+        }
+
+        let mut entity_commands = if_none_continue!(commands.get_entity(*entity));
+
+        if parent.is_some() {
+            entity_commands
+                .remove_parent()
+                .insert(global_transform.compute_transform());
+        }
+
+        match layer.as_ref().filter(|name| layers.get(name).is_some()) {
+            Some(name) => {
+                entity_commands.insert(LevelLayer { name: name.clone() });
+            },
+            None => {
+                entity_commands
+                    .remove::<LevelLayer>()
+                    .insert(Visibility::Inherited);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colliding_file_names_are_rejected() {
+        let mut layers = LevelLayers::default();
+
+        assert!(layers.add("a b"));
+        assert!(!layers.add("a.b"));
+        assert!(!layers.add("A_B"));
+        assert!(layers.add("c"));
+        assert_eq!(layers.layers.len(), 2);
+    }
+
+    #[test]
+    fn layer_paths_follow_the_saved_level() {
+        let mut layers = LevelLayers::default();
+        layers.add("props");
+
+        let old = Path::new("levels/old.ron");
+        let new = Path::new("levels/new.ron");
+
+        layers.saved(old);
+
+        assert_eq!(layers.layers[0].path, PathBuf::from("old.props.ron"));
+        assert_eq!(layers.file_path(&layers.layers[0], old), PathBuf::from("levels/old.props.ron"));
+
+        // Save as.
+        assert_eq!(layers.to_ron(new)[0].path, PathBuf::from("new.props.ron"));
+        assert_eq!(layers.file_path(&layers.layers[0], new), PathBuf::from("levels/new.props.ron"));
+
+        layers.saved(new);
+
+        assert_eq!(layers.layers[0].path, PathBuf::from("new.props.ron"));
+    }
+}
@@ -0,0 +1,174 @@
+// Frozen copies of older level layouts, read only by the migration steps. They must not use
+// the live level types, so that changes of `Object` or `Ron` don't change how old files are read.
+
+pub mod v1 {
+    use std::path::PathBuf;
+    use bevy::utils::HashMap;
+    use ::serde::{Serialize, Deserialize};
+
+    #[derive(Serialize, Deserialize)]
+    pub enum ColliderType {
+        FromBevyMesh,
+        Ball(u32),
+        Cuboid((u32, u32, u32)),
+        Cylinder((u32, u32)),
+        Cone((u32, u32)),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct ColliderData {
+        pub friction: u32,
+        pub fixed: bool,
+        pub collision_group_self: u32,
+        pub collision_group_filter: u32,
+        pub solver_group_self: u32,
+        pub solver_group_filter: u32,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Collider {
+        pub collider_type: ColliderType,
+        pub collider_data: ColliderData,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum ObjectType {
+        Empty,
+        Scene,
+        Mesh,
+        Ron,
+        Collider,
+    }
+
+    // Objects keep this layout up to v6.
+    #[derive(Serialize, Deserialize)]
+    pub struct Object {
+        pub object_type: ObjectType,
+        pub path: Option<PathBuf>,
+        pub collider: Option<Collider>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Ron {
+        pub objects: HashMap<usize, Object>,
+        pub nodes: HashMap<usize, RonNode>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct RonNode {
+        pub transform: [f32; 16],
+        pub object: usize,
+        pub has_parent: bool,
+        pub childrens: Vec<usize>,
+    }
+}
+
+pub mod v2 {
+    use bevy::utils::HashMap;
+    use ::serde::{Serialize, Deserialize};
+
+    pub use super::v1::{Object, RonNode};
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Ron {
+        pub version: u32,
+        pub objects: HashMap<usize, Object>,
+        pub nodes: HashMap<usize, RonNode>,
+    }
+}
+
+pub mod v3 {
+    use bevy::utils::{HashMap, Uuid};
+    use ::serde::{Serialize, Deserialize};
+
+    pub use super::v1::Object;
+
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+    pub struct EditorId(pub Uuid);
+
+    impl EditorId {
+        pub fn new() -> Self {
+            Self(Uuid::new_v4())
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Ron {
+        pub version: u32,
+        pub objects: HashMap<usize, Object>,
+        pub nodes: Vec<RonNode>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct RonNode {
+        pub id: EditorId,
+        pub transform: [f32; 16],
+        pub object: usize,
+        pub has_parent: bool,
+        pub childrens: Vec<EditorId>,
+    }
+}
+
+pub mod v4 {
+    use ::serde::{Serialize, Deserialize};
+
+    pub use super::v1::Object;
+    pub use super::v3::EditorId;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct RonTransform {
+        pub translation: [f32; 3],
+        pub rotation: [f32; 4],
+        pub scale: [f32; 3],
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Ron {
+        pub version: u32,
+        pub objects: Vec<Object>,
+        pub nodes: Vec<RonNode>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct RonNode {
+        pub id: EditorId,
+        pub transform: RonTransform,
+        pub object: usize,
+        pub has_parent: bool,
+        pub childrens: Vec<EditorId>,
+    }
+}
+
+// Nodes got reflected components with a serde default, v4 levels read as v5.
+// v6 only changed the object paths and has the same layout.
+pub mod v5 {
+    use ::serde::{Serialize, Deserialize};
+
+    pub use super::v1::Object;
+    pub use super::v3::EditorId;
+    pub use super::v4::RonTransform;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct RonComponent {
+        pub type_name: String,
+        pub value: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Ron {
+        pub version: u32,
+        pub objects: Vec<Object>,
+        pub nodes: Vec<RonNode>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct RonNode {
+        pub id: EditorId,
+        pub transform: RonTransform,
+        pub object: usize,
+        pub has_parent: bool,
+        pub childrens: Vec<EditorId>,
+        #[serde(default)]
+        pub components: Vec<RonComponent>,
+    }
+}
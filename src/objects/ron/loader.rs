@@ -3,10 +3,54 @@ use bevy::asset::{AssetLoader, LoadedAsset};
 use bevy::reflect::serde::UntypedReflectDeserializer;
 use bevy_reflect::FromReflect;
 
+use crate::objects::{LoadErrorQueue, LoadErrorEvent};
+
 use super::Ron;
+use super::migration::{RonError, RonMigrations, RON_VERSION, read_version};
+use super::binary::{deserialize_binary, is_binary, BINARY_EXTENSION};
 
 #[derive(Default)]
-pub struct RonLoader;
+pub struct RonLoader {
+    pub migrations: RonMigrations,
+    pub errors: LoadErrorQueue,
+}
+
+impl RonLoader {
+    // Text or binary level, told apart by the binary header.
+    pub fn parse_bytes(&self, bytes: &[u8]) -> Result<Ron, RonError> {
+        if is_binary(bytes) {
+            return deserialize_binary(bytes);
+        }
+
+        let serialized = std::str::from_utf8(bytes).map_err(|e| RonError::Parse(e.to_string()))?;
+
+        self.parse(serialized)
+    }
+
+    pub fn parse(&self, serialized: &str) -> Result<Ron, RonError> {
+        let version = read_version(serialized)?;
+
+        let serialized = if version != RON_VERSION {
+            self.migrations.migrate(serialized, version)?
+        } else {
+            serialized.to_string()
+        };
+
+        let mut registry = bevy::reflect::TypeRegistryInternal::new();
+        registry.register::<Ron>();
+        let reflect_deserializer = UntypedReflectDeserializer::new(&registry);
+        let mut deserializer = ron::de::Deserializer::from_str(&serialized)?;
+        let reflect_value = reflect_deserializer.deserialize(&mut deserializer)?;
+
+        let mut ron = Ron::from_reflect(&*reflect_value)
+            .ok_or(RonError::Reflect("value is not a Ron level".to_string()))?;
+
+        // Steps that keep the layout don't stamp their version.
+        ron.version = RON_VERSION;
+
+        Ok(ron)
+    }
+}
 
 impl AssetLoader for RonLoader {
     fn load<'a>(
@@ -17,33 +61,22 @@ impl AssetLoader for RonLoader {
         Box::pin(async move {
             // let res: Ron = ron::de::from_bytes(bytes)?;
 
-            let mut assets_path = std::env::current_exe()?;
-            assets_path.pop();
-            assets_path.push("assets");
-
-            let assets_path = assets_path
-                .to_str()
-                .expect("RonLoader assets_path error: can't replace exe path")
-                .replace("\\", "\\\\");
-
-            let serialized = std::string::String::from_utf8(Vec::from(bytes))?
-                .replace("__path_to_assets__", &assets_path);
-
-            let mut registry = bevy::reflect::TypeRegistryInternal::new();
-            registry.register::<Ron>();
-            let reflect_deserializer = UntypedReflectDeserializer::new(&registry);
-            let mut deserializer = ron::de::Deserializer::from_str(&serialized)?;
-            let reflect_value = reflect_deserializer.deserialize(&mut deserializer)?;
-            let ron = Ron::from_reflect(&*reflect_value).expect("RonLoader: Failed to deserialize ron");
+            let ron = match self.parse_bytes(bytes) {
+                Ok(ron) => ron,
+                Err(e) => {
+                    self.errors.push(LoadErrorEvent::new(Some(load_context.path().to_path_buf()), None, e.to_string()));
+                    return Err(e.into());
+                },
+            };
 
             load_context.set_default_asset(LoadedAsset::new(ron));
 
-            log::info!("RonLoader load ok, {}",  serialized);
+            log::info!("RonLoader load ok, {:?}", load_context.path());
 
             Ok(())
         })
     }
     fn extensions(&self) -> &[&str] {
-        &["ron"]
+        &["ron", BINARY_EXTENSION]
     }
 }
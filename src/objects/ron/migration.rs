@@ -0,0 +1,349 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use bevy::prelude::Mat4;
+use bevy::utils::HashMap;
+use ::serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use super::legacy;
+use super::sawer::pretty_config;
+use super::Ron;
+
+// Current level format, stamped into every saved level. Older editors refuse newer levels,
+// so it is only bumped when old levels can't be read with serde defaults.
+pub const RON_VERSION: u32 = 15;
+
+// Levels saved before the version field existed.
+const UNVERSIONED: u32 = 1;
+
+const LEGACY_ASSETS_MARKER: &str = "__path_to_assets__";
+
+#[derive(Debug)]
+pub enum RonError {
+    Parse(String),
+    Reflect(String),
+    UnsupportedVersion { found: u32, supported: u32 },
+    MissingMigration(u32),
+}
+
+impl fmt::Display for RonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonError::Parse(e) => write!(f, "failed to parse level: {}", e),
+            RonError::Reflect(e) => write!(f, "failed to build level: {}", e),
+            RonError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "level format version {} is newer than the editor supports ({}), update the editor to open this file",
+                found, supported
+            ),
+            RonError::MissingMigration(version) => write!(f, "no migration registered from level format version {}", version),
+        }
+    }
+}
+
+impl std::error::Error for RonError {}
+
+impl From<ron::error::SpannedError> for RonError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        RonError::Parse(e.to_string())
+    }
+}
+
+impl From<ron::Error> for RonError {
+    fn from(e: ron::Error) -> Self {
+        RonError::Parse(e.to_string())
+    }
+}
+
+pub type MigrationStep = fn(&str) -> Result<String, RonError>;
+
+// Chain of steps, each one lifting a serialized level from its version to the next one.
+#[derive(Clone)]
+pub struct RonMigrations {
+    steps: HashMap<u32, (u32, MigrationStep)>,
+}
+
+impl Default for RonMigrations {
+    fn default() -> Self {
+        let mut migrations = Self { steps: HashMap::new() };
+
+        migrations.register(1, migrate_v1_to_v2);
+        migrations.register(2, migrate_v2_to_v3);
+        migrations.register(3, migrate_v3_to_v4);
+        migrations.register(4, keep_layout);
+        migrations.register(5, migrate_v5_to_v6);
+        // Versions 7 to 14 only added fields with serde defaults.
+        migrations.register_range(6..=13, keep_layout);
+        // Component values became inline, the ron text of older levels is read when the
+        // components are inserted, with the type registry of the app.
+        migrations.register(14, keep_layout);
+
+        migrations
+    }
+}
+
+impl RonMigrations {
+    pub fn register(&mut self, from: u32, step: MigrationStep) -> &mut Self {
+        self.steps.insert(from, (from + 1, step));
+        self
+    }
+
+    // One step for versions with the same layout, lifting each of them past the range.
+    pub fn register_range(&mut self, from: RangeInclusive<u32>, step: MigrationStep) -> &mut Self {
+        for version in from.clone() {
+            self.steps.insert(version, (from.end() + 1, step));
+        }
+        self
+    }
+
+    pub fn migrate(&self, serialized: &str, version: u32) -> Result<String, RonError> {
+        if version > RON_VERSION {
+            return Err(RonError::UnsupportedVersion { found: version, supported: RON_VERSION });
+        }
+
+        let mut serialized = serialized.to_string();
+        let mut from = version;
+
+        while from < RON_VERSION {
+            let (to, step) = *self.steps.get(&from).ok_or(RonError::MissingMigration(from))?;
+            serialized = step(&serialized)?;
+
+            log::info!("RonMigrations: level migrated from version {} to {}", from, to);
+
+            from = to;
+        }
+
+        Ok(serialized)
+    }
+}
+
+#[derive(Deserialize)]
+struct RonHeader {
+    #[serde(default = "unversioned")]
+    version: u32,
+}
+
+fn unversioned() -> u32 {
+    UNVERSIONED
+}
+
+pub fn read_version(serialized: &str) -> Result<u32, RonError> {
+    let header: HashMap<String, RonHeader> = ron::de::from_str(serialized)?;
+
+    header
+        .into_values()
+        .next()
+        .map(|header| header.version)
+        .ok_or(RonError::Parse("empty level".to_string()))
+}
+
+// Levels are written by `ReflectSerializer` as `{ "type::Name": (..) }`,
+// the steps keep that wrapper so the result can go through the reflect deserializer.
+fn read_wrapped<T: DeserializeOwned>(serialized: &str) -> Result<T, RonError> {
+    let wrapped: HashMap<String, T> = ron::de::from_str(serialized)?;
+
+    wrapped
+        .into_values()
+        .next()
+        .ok_or(RonError::Parse("empty level".to_string()))
+}
+
+fn write_wrapped<T: Serialize>(value: &T) -> Result<String, RonError> {
+    let mut wrapped = HashMap::new();
+    wrapped.insert(std::any::type_name::<Ron>(), value);
+
+    Ok(ron::ser::to_string_pretty(&wrapped, pretty_config())?)
+}
+
+// For versions that only added fields with serde defaults.
+fn keep_layout(serialized: &str) -> Result<String, RonError> {
+    Ok(serialized.to_string())
+}
+
+fn migrate_v1_to_v2(serialized: &str) -> Result<String, RonError> {
+    let old: legacy::v1::Ron = read_wrapped(serialized)?;
+
+    write_wrapped(&legacy::v2::Ron {
+        version: 2,
+        objects: old.objects,
+        nodes: old.nodes,
+    })
+}
+
+// Index keys are replaced with persistent ids.
+fn migrate_v2_to_v3(serialized: &str) -> Result<String, RonError> {
+    let old: legacy::v2::Ron = read_wrapped(serialized)?;
+
+    let ids: HashMap<usize, legacy::v3::EditorId> = old.nodes
+        .keys()
+        .map(|key| (*key, legacy::v3::EditorId::new()))
+        .collect();
+
+    let mut nodes = Vec::new();
+
+    for (key, node) in old.nodes.into_iter() {
+        let mut childrens = Vec::new();
+
+        for child in node.childrens.iter() {
+            childrens.push(*ids.get(child).ok_or(RonError::Parse(format!("node {} has missing child {}", key, child)))?);
+        }
+
+        nodes.push(legacy::v3::RonNode {
+            id: ids[&key],
+            transform: node.transform,
+            object: node.object,
+            has_parent: node.has_parent,
+            childrens,
+        });
+    }
+
+    write_wrapped(&legacy::v3::Ron {
+        version: 3,
+        objects: old.objects,
+        nodes,
+    })
+}
+
+// Matrix transforms become translation/rotation/scale, objects are a list in the order of their keys.
+fn migrate_v3_to_v4(serialized: &str) -> Result<String, RonError> {
+    let old: legacy::v3::Ron = read_wrapped(serialized)?;
+
+    let mut objects: Vec<(usize, legacy::v4::Object)> = old.objects.into_iter().collect();
+    objects.sort_by_key(|(key, _)| *key);
+
+    let remap: HashMap<usize, usize> = objects
+        .iter()
+        .enumerate()
+        .map(|(index, (key, _))| (*key, index))
+        .collect();
+
+    let mut nodes = Vec::new();
+
+    for node in old.nodes.into_iter() {
+        let (scale, rotation, translation) = Mat4::from_cols_array(&node.transform).to_scale_rotation_translation();
+
+        nodes.push(legacy::v4::RonNode {
+            id: node.id,
+            transform: legacy::v4::RonTransform {
+                translation: translation.to_array(),
+                rotation: rotation.to_array(),
+                scale: scale.to_array(),
+            },
+            object: *remap.get(&node.object).ok_or(RonError::Parse(format!("node {:?} has missing object {}", node.id, node.object)))?,
+            has_parent: node.has_parent,
+            childrens: node.childrens,
+        });
+    }
+
+    write_wrapped(&legacy::v4::Ron {
+        version: 4,
+        objects: objects.into_iter().map(|(_, object)| object).collect(),
+        nodes,
+    })
+}
+
+// The saver replaced the assets folder with `__path_to_assets__`, object paths become
+// relative to the default asset root.
+fn migrate_v5_to_v6(serialized: &str) -> Result<String, RonError> {
+    let mut ron: legacy::v5::Ron = read_wrapped(serialized)?;
+
+    for object in ron.objects.iter_mut() {
+        let path = match object.path.as_ref() {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => continue,
+        };
+
+        match path.strip_prefix(LEGACY_ASSETS_MARKER) {
+            Some(relative) => {
+                let relative = relative
+                    .trim_start_matches(|c| c == '/' || c == '\\')
+                    .replace('\\', "/");
+
+                object.path = Some(PathBuf::from(relative));
+            }
+            None => log::warn!("migrate_v5_to_v6: object path {} is not inside the assets folder", path),
+        }
+    }
+
+    ron.version = 6;
+
+    write_wrapped(&ron)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::objects::{ColliderType, ObjectType};
+    use crate::objects::ron::{serialize_ron, RonLoader};
+    use super::*;
+
+    // A level saved before the version field, with index keys and matrix transforms.
+    const V1_LEVEL: &str = r#"{
+    "kns_bevy_editor3d::objects::ron::Ron": (
+        objects: {
+            0: (
+                object_type: Scene,
+                path: Some("__path_to_assets__/gltf/ground_4/scene.gltf"),
+                collider: Some((collider_type: Cuboid((1, 2, 3)), collider_data: (friction: 3000, fixed: true, collision_group_self: 1, collision_group_filter: 1, solver_group_self: 1, solver_group_filter: 1))),
+            ),
+            1: (object_type: Empty, path: None, collider: None),
+        },
+        nodes: {
+            0: (transform: (1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0), object: 1, has_parent: false, childrens: [1]),
+            1: (transform: (1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 5.0, 0.0, 0.0, 1.0), object: 0, has_parent: true, childrens: []),
+        },
+    ),
+}"#;
+
+    #[test]
+    fn v1_level_migrates_to_current() {
+        assert_eq!(read_version(V1_LEVEL).unwrap(), UNVERSIONED);
+
+        let loader = RonLoader::default();
+        let ron = loader.parse(V1_LEVEL).unwrap();
+
+        assert_eq!(ron.version, RON_VERSION);
+        assert_eq!(ron.objects.len(), 2);
+        assert_eq!(ron.nodes.len(), 2);
+
+        let root = ron.nodes.iter().find(|node| !node.has_parent).unwrap();
+        let child = ron.nodes.iter().find(|node| node.has_parent).unwrap();
+
+        assert_eq!(root.childrens, vec![child.id]);
+        assert_eq!(ron.objects[root.object].object_type, ObjectType::Empty);
+        assert_eq!(child.transform.translation, [5., 0., 0.]);
+
+        let scene = &ron.objects[child.object];
+
+        assert_eq!(scene.object_type, ObjectType::Scene);
+        assert_eq!(scene.path, Some(PathBuf::from("gltf/ground_4/scene.gltf")));
+        assert_eq!(scene.collider.as_ref().unwrap().collider_type, ColliderType::Cuboid((1, 2, 3)));
+
+        let saved = serialize_ron(&ron).unwrap();
+
+        assert_eq!(loader.parse(&saved).unwrap(), ron);
+    }
+
+    #[test]
+    fn versions_with_the_same_layout_read_as_current() {
+        let level = r#"{ "kns_bevy_editor3d::objects::ron::Ron": (version: 9, objects: [(object_type: Empty, path: None, collider: None)], nodes: []) }"#;
+
+        let ron = RonLoader::default().parse(level).unwrap();
+
+        assert_eq!(ron.version, RON_VERSION);
+        assert_eq!(ron.objects.len(), 1);
+    }
+
+    #[test]
+    fn newer_version_is_refused() {
+        let level = format!(
+            r#"{{ "kns_bevy_editor3d::objects::ron::Ron": (version: {}, objects: [], nodes: []) }}"#,
+            RON_VERSION + 1,
+        );
+
+        assert!(matches!(
+            RonLoader::default().parse(&level),
+            Err(RonError::UnsupportedVersion { .. })
+        ));
+    }
+}
@@ -5,7 +5,7 @@ use bevy::asset::{HandleId, LoadState};
 use bevy::prelude::*;
 use bevy_gltf::{GltfMesh, GltfNode};
 use ::serde::{Serialize, Deserialize, de::DeserializeSeed};
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
 use bevy::log;
 use bevy::{
     reflect::{
@@ -14,16 +14,42 @@ use bevy::{
     },
 };
 
-use self::loader::RonLoader;
+pub use self::loader::RonLoader;
 use self::spawn::{process_add_ron, process_spawn_ron};
-use self::sawer::process_save_ron;
+use self::sawer::{process_save_ron, process_save_ron_path};
+use self::autosave::{AutosaveState, check_recovery, process_restored_path};
+pub use self::autosave::AutosaveSettings;
+pub(crate) use self::autosave::process_autosave;
+pub use self::migration::{RonError, RonMigrations, RON_VERSION};
+pub use self::components::{RonComponent, RonComponentValue, SavedComponents};
+pub(crate) use self::components::{reflect_components, InsertReflectComponents};
+pub use self::sawer::serialize_ron;
+pub use self::binary::{serialize_binary, deserialize_binary, serialize_level, BINARY_EXTENSION};
+pub use self::validate::RonIssue;
+pub use self::dependencies::{RonDependencies, format_chain};
+pub use self::layers::{RonLayer, LevelLayer, LevelLayers, Layer, MoveToLayerEvent, world_layer, entity_layer};
+use self::layers::{process_spawn_layers, process_layers, process_move_to_layer};
+pub use self::settings::LevelSettings;
+use self::settings::apply_level_settings;
+pub use self::prefab::{RonOverride, PrefabInstance, PrefabNode, ApplyPrefabEvent, RevertPrefabEvent, CreatePrefabEvent, prefab_instance_entity};
+use self::prefab::{PrefabSources, process_apply_prefab, process_create_prefab, process_revert_prefab, process_modified_prefabs, process_rebuild_prefabs};
 
-use super::Object;
+use super::{Object, EditorId, LoadErrorQueue};
 
 
+mod autosave;
+mod binary;
+mod components;
+mod dependencies;
 mod loader;
+mod layers;
+mod legacy;
+mod migration;
+mod prefab;
 mod sawer;
+mod settings;
 mod spawn;
+mod validate;
 
 
 pub struct LoadRonEvent {
@@ -33,6 +59,8 @@ pub struct LoadRonEvent {
 pub struct SaveRonEvent {
     pub path: Option<PathBuf>,    
     pub root: Option<Entity>, 
+    // Autosave snapshots don't change the current file.
+    pub snapshot: bool,
 }
 
 #[derive(Clone)]
@@ -43,8 +71,10 @@ pub struct SpawnRonEvent {
 #[derive(Clone)]
 pub struct AddRonEvent {
     pub entity: Entity,     
+    pub id: EditorId,
     pub handle: Handle<Ron>,   
     pub transform: Transform,
+    pub overrides: Vec<RonOverride>,
 }
 
 
@@ -52,8 +82,97 @@ pub struct AddRonEvent {
 #[uuid = "05232afa-11b7-42ba-9217-de0f6f0fe88d"]
 #[reflect(Serialize, Deserialize)]
 pub struct Ron {
-    pub objects: HashMap<usize, Object>,
-    pub nodes: HashMap<usize, RonNode>,
+    pub version: u32,
+    pub objects: Vec<Object>,
+    pub nodes: Vec<RonNode>,
+    // Only for master levels.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<RonLayer>,
+    // Only for master levels, written without `Some` like before it was optional.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "settings::serialize_settings",
+        deserialize_with = "settings::deserialize_settings",
+    )]
+    pub settings: Option<LevelSettings>,
+    // Only for autosave snapshots: the layers of the level, their objects are in the snapshot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_layers: Option<Vec<RonLayer>>,
+}
+
+impl Ron {
+    // Objects in a stable sort, nodes in hierarchy order with roots sorted by id,
+    // so that saving an unchanged level gives the same file.
+    pub fn sort(&mut self) {
+        let mut objects: Vec<(usize, Object)> = self.objects.drain(..).enumerate().collect();
+        objects.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+        let mut remap = vec![0; objects.len()];
+
+        for (new_index, (old_index, _)) in objects.iter().enumerate() {
+            remap[*old_index] = new_index;
+        }
+
+        self.objects = objects.into_iter().map(|(_, object)| object).collect();
+
+        for node in self.nodes.iter_mut() {
+            node.object = remap.get(node.object).copied().unwrap_or(node.object);
+        }
+
+        let childrens: HashSet<EditorId> = self.nodes
+            .iter()
+            .flat_map(|node| node.childrens.iter().copied())
+            .collect();
+
+        let mut roots: Vec<EditorId> = self.nodes
+            .iter()
+            .filter(|node| !childrens.contains(&node.id))
+            .map(|node| node.id)
+            .collect();
+        roots.sort();
+
+        let mut nodes: HashMap<EditorId, RonNode> = self.nodes.drain(..).map(|node| (node.id, node)).collect();
+        let mut stack: Vec<EditorId> = roots.into_iter().rev().collect();
+
+        while let Some(id) = stack.pop() {
+            if let Some(node) = nodes.remove(&id) {
+                stack.extend(node.childrens.iter().rev());
+                self.nodes.push(node);
+            }
+        }
+
+        let mut rest: Vec<RonNode> = nodes.into_values().collect();
+        rest.sort_by_key(|node| node.id);
+        self.nodes.append(&mut rest);
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct RonTransform {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+impl From<&Transform> for RonTransform {
+    fn from(transform: &Transform) -> Self {
+        Self {
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            scale: transform.scale.to_array(),
+        }
+    }
+}
+
+impl From<&RonTransform> for Transform {
+    fn from(transform: &RonTransform) -> Self {
+        Transform {
+            translation: Vec3::from_array(transform.translation),
+            rotation: Quat::from_array(transform.rotation),
+            scale: Vec3::from_array(transform.scale),
+        }
+    }
 }
 
 /* #[derive(Default, Debug, Clone, PartialEq, Resource, Reflect, FromReflect, Serialize, Deserialize, TypeUuid)]
@@ -61,10 +180,19 @@ pub struct Ron {
 #[reflect(Serialize, Deserialize)] */
 #[derive(Default, Debug, Clone, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
 pub struct RonNode {
-    pub transform: [f32; 16],
+    pub id: EditorId,
+    pub transform: RonTransform,
     pub object: usize,
     pub has_parent: bool,
-    pub childrens: Vec<usize>,
+    pub childrens: Vec<EditorId>,
+    #[serde(default)]
+    pub components: Vec<RonComponent>,
+    // Only for nested ron objects.
+    #[serde(default)]
+    pub overrides: Vec<RonOverride>,
+    // Only for root nodes of autosave snapshots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
 }
 
 impl Eq for RonNode {
@@ -72,7 +200,11 @@ impl Eq for RonNode {
 
 impl Hash for RonNode {
         fn hash<H: Hasher>(&self, state: &mut H) {
-        for v in self.transform {
+        self.id.hash(state);
+
+        for v in self.transform.translation.iter()
+            .chain(self.transform.rotation.iter())
+            .chain(self.transform.scale.iter()) {
             ((v*1000.) as i64).hash(state);
         }
 
@@ -85,17 +217,46 @@ pub struct RonPlugin;
 impl Plugin for RonPlugin {
     fn build(&self, app: &mut App) {
 
+        let errors = LoadErrorQueue::default();
+
         app
             .add_asset::<Ron>()
-            .add_asset_loader(RonLoader)
+            .insert_resource(errors.clone())
+            .add_asset_loader(RonLoader { errors, ..Default::default() })
+            .init_resource::<SavedComponents>()
+            .init_resource::<AutosaveSettings>()
+            .init_resource::<AutosaveState>()
+            .init_resource::<PrefabSources>()
+            .init_resource::<RonDependencies>()
+            .init_resource::<LevelLayers>()
+            .init_resource::<LevelSettings>()
             .add_event::<LoadRonEvent>() 
             .add_event::<SaveRonEvent>()   
             .add_event::<AddRonEvent>()           
             .add_event::<SpawnRonEvent>()           
+            .add_event::<ApplyPrefabEvent>()
+            .add_event::<RevertPrefabEvent>()
+            .add_event::<CreatePrefabEvent>()
+            .add_event::<MoveToLayerEvent>()
+            .add_startup_system(check_recovery)
             .add_systems((
+                process_autosave.before(process_save_ron),
                 process_save_ron,
+                process_save_ron_path.after(process_save_ron),
+                process_restored_path,
                 process_spawn_ron,
-                process_add_ron
+                process_add_ron,
+                process_apply_prefab,
+                process_revert_prefab,
+                process_create_prefab.after(process_save_ron),
+                process_modified_prefabs,
+                process_rebuild_prefabs,
+            ))
+            .add_systems((
+                process_spawn_layers.after(process_spawn_ron),
+                process_layers,
+                process_move_to_layer,
+                apply_level_settings.after(process_spawn_ron),
             ))
             ;
     }
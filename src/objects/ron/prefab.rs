@@ -0,0 +1,576 @@
+use std::path::PathBuf;
+use bevy::asset::{HandleId, LoadState};
+use bevy::ecs::system::Command;
+use bevy::{log, prelude::*, utils::HashMap};
+use ::serde::{Serialize, Deserialize};
+
+use crate::gui::{FileState, MyEditorState, SelectState};
+use crate::objects::{Object, ObjectType, EditorId, EditorIdMap, AddObjectEvent, MissingAsset};
+use crate::picking::PickingEvent;
+use crate::{if_err_continue, if_err_return, if_none_continue};
+
+use super::{Ron, RonNode, RonTransform, RonComponent, AddRonEvent, RON_VERSION};
+use super::binary::serialize_level;
+use super::components::collect_components;
+use super::layers::LevelLayer;
+
+// Fields of a prefab node changed in one instance, `node` is the id of the node in the prefab file.
+#[derive(Default, Debug, Clone, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct RonOverride {
+    pub node: EditorId,
+    #[serde(default)]
+    pub transform: Option<RonTransform>,
+    #[serde(default)]
+    pub object: Option<Object>,
+    #[serde(default)]
+    pub components: Option<Vec<RonComponent>>,
+}
+
+// Root of a nested ron object.
+#[derive(Component, Debug, Clone)]
+pub struct PrefabInstance {
+    pub handle: Handle<Ron>,
+}
+
+// Entity spawned from the node `node` of the prefab of `instance`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PrefabNode {
+    pub instance: EditorId,
+    pub node: EditorId,
+}
+
+pub struct ApplyPrefabEvent {
+    pub entity: Entity,
+}
+
+pub struct RevertPrefabEvent {
+    pub entity: Entity,
+}
+
+// Sent together with `SaveRonEvent { root: Some(entity) }`, the subtree is replaced
+// by an instance once the saved file is loaded.
+pub struct CreatePrefabEvent {
+    pub entity: Entity,
+    pub path: PathBuf,
+}
+
+pub(crate) struct PendingPrefab {
+    entity: Entity,
+    path: PathBuf,
+    handle: Handle<Ron>,
+}
+
+// Prefabs as they were when their instances were spawned, to keep the overrides
+// of the instances when a prefab is changed.
+#[derive(Resource, Default)]
+pub(crate) struct PrefabSources {
+    rons: HashMap<HandleId, Ron>,
+}
+
+impl PrefabSources {
+    pub(crate) fn insert(&mut self, handle: &Handle<Ron>, ron: &Ron) {
+        if self.rons.get(&handle.id()) != Some(ron) {
+            self.rons.insert(handle.id(), ron.clone());
+        }
+    }
+}
+
+// The instance is rebuilt from the changed prefab.
+#[derive(Component)]
+pub(crate) struct RebuildPrefab {
+    overrides: Vec<RonOverride>,
+}
+
+impl RonNode {
+    // `None` if the node refers to a missing object.
+    pub(crate) fn with_override(&self, ron_override: Option<&RonOverride>, ron: &Ron) -> Option<(Transform, Object, Vec<RonComponent>)> {
+        let transform = ron_override
+            .and_then(|ron_override| ron_override.transform.as_ref())
+            .unwrap_or(&self.transform);
+
+        let object = ron_override
+            .and_then(|ron_override| ron_override.object.clone())
+            .or_else(|| ron.objects.get(self.object).cloned())?;
+
+        let components = ron_override
+            .and_then(|ron_override| ron_override.components.clone())
+            .unwrap_or_else(|| self.components.clone());
+
+        Some((transform.into(), object, components))
+    }
+}
+
+// Differences between the spawned prefab nodes of the instance and its prefab.
+// Nodes of prefabs nested in the prefab are not tracked.
+pub(crate) fn collect_overrides(world: &World, entity: Entity) -> Vec<RonOverride> {
+    let ron = world
+        .get::<PrefabInstance>(entity)
+        .and_then(|instance| world.resource::<Assets<Ron>>().get(&instance.handle));
+
+    // A missing prefab keeps the overrides it was loaded with.
+    match ron {
+        Some(ron) => collect_overrides_from(world, entity, ron),
+        None => world.get::<MissingAsset>(entity).map_or(Vec::new(), |missing| missing.overrides.clone()),
+    }
+}
+
+// `ron` is the prefab the instance was spawned from.
+fn collect_overrides_from(world: &World, entity: Entity, ron: &Ron) -> Vec<RonOverride> {
+    let mut overrides = Vec::new();
+
+    let id = match world.get::<EditorId>(entity) {
+        Some(id) => id,
+        None => return overrides,
+    };
+
+    let nodes: HashMap<EditorId, &RonNode> = ron.nodes.iter().map(|node| (node.id, node)).collect();
+
+    collect_node_overrides(world, entity, id, ron, &nodes, &mut overrides);
+
+    overrides.sort_by_key(|ron_override| ron_override.node);
+    overrides
+}
+
+fn collect_node_overrides(
+    world: &World,
+    entity: Entity,
+    instance: &EditorId,
+    ron: &Ron,
+    nodes: &HashMap<EditorId, &RonNode>,
+    overrides: &mut Vec<RonOverride>,
+) {
+    let children = match world.get::<Children>(entity) {
+        Some(children) => children,
+        None => return,
+    };
+
+    for child in children.iter() {
+        let prefab_node = if_none_continue!(world.get::<PrefabNode>(*child));
+
+        if prefab_node.instance != *instance {
+            continue;
+        }
+
+        let node = if_none_continue!(nodes.get(&prefab_node.node));
+        let mut ron_override = RonOverride { node: node.id, ..default() };
+
+        if let Some(transform) = world.get::<Transform>(*child) {
+            let transform = RonTransform::from(transform);
+
+            if !transform.approx_eq(&node.transform) {
+                ron_override.transform = Some(transform);
+            }
+        }
+
+        if let Some(object) = world.get::<Object>(*child) {
+            if ron.objects.get(node.object) != Some(object) {
+                ron_override.object = Some(object.clone());
+            }
+        }
+
+        let components = collect_components(world, *child);
+
+        if components != node.components {
+            ron_override.components = Some(components);
+        }
+
+        if ron_override != (RonOverride { node: node.id, ..default() }) {
+            overrides.push(ron_override);
+        }
+
+        collect_node_overrides(world, *child, instance, ron, nodes, overrides);
+    }
+}
+
+impl RonTransform {
+    pub fn approx_eq(&self, other: &RonTransform) -> bool {
+        self.translation.iter().chain(self.rotation.iter()).chain(self.scale.iter())
+            .zip(other.translation.iter().chain(other.rotation.iter()).chain(other.scale.iter()))
+            .all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+}
+
+impl Ron {
+    pub fn apply_overrides(&mut self, overrides: &[RonOverride]) {
+        for ron_override in overrides.iter() {
+            let index = if_none_continue!(self.nodes.iter().position(|node| node.id == ron_override.node));
+
+            if let Some(object) = &ron_override.object {
+                self.nodes[index].object = match self.objects.iter().position(|o| o == object) {
+                    Some(object_index) => object_index,
+                    None => {
+                        self.objects.push(object.clone());
+                        self.objects.len() - 1
+                    }
+                };
+            }
+
+            let node = &mut self.nodes[index];
+
+            if let Some(transform) = &ron_override.transform {
+                node.transform = transform.clone();
+            }
+
+            if let Some(components) = &ron_override.components {
+                node.components = components.clone();
+            }
+        }
+
+        self.remove_unused_objects();
+    }
+
+    // Nodes with an out of range object index are dropped, like the spawn path they can't be
+    // restored from the file.
+    fn remove_unused_objects(&mut self) {
+        let objects_len = self.objects.len();
+        let mut dropped = Vec::new();
+
+        self.nodes.retain(|node| {
+            if node.object < objects_len {
+                return true;
+            }
+
+            log::error!("remove_unused_objects: node {:?}: object index {} is out of range", node.id, node.object);
+            dropped.push(node.id);
+            false
+        });
+
+        for node in self.nodes.iter_mut() {
+            node.childrens.retain(|child| !dropped.contains(child));
+        }
+
+        let mut remap = vec![None; objects_len];
+        let mut objects = Vec::new();
+
+        for node in self.nodes.iter_mut() {
+            let index = match remap[node.object] {
+                Some(index) => index,
+                None => {
+                    let index = objects.len();
+                    objects.push(self.objects[node.object].clone());
+                    remap[node.object] = Some(index);
+                    index
+                }
+            };
+
+            node.object = index;
+        }
+
+        self.objects = objects;
+    }
+}
+
+struct SetRonAsset {
+    handle: Handle<Ron>,
+    ron: Ron,
+}
+
+impl Command for SetRonAsset {
+    fn write(self, world: &mut World) {
+        world.resource_mut::<Assets<Ron>>().set_untracked(self.handle, self.ron);
+    }
+}
+
+// Bakes the overrides of the instance into the prefab file.
+pub(crate) fn process_apply_prefab(
+    world: &World,
+    mut commands: Commands,
+    mut reader: EventReader<ApplyPrefabEvent>,
+) {
+    for ApplyPrefabEvent { entity } in reader.iter() {
+        let instance = if_none_continue!(world.get::<PrefabInstance>(*entity));
+        let object = if_none_continue!(world.get::<Object>(*entity));
+        let mut ron = if_none_continue!(world.resource::<Assets<Ron>>().get(&instance.handle)).clone();
+
+        let path = if_none_continue!(object.path.as_ref());
+        let file_state = world.resource::<FileState>();
+        let file_path = if_none_continue!(file_state.to_file_path(path));
+
+        log::info!("process_apply_prefab {:?}", file_path);
+
+        ron.apply_overrides(&collect_overrides(world, *entity));
+        ron.version = RON_VERSION;
+        ron.sort();
+
+        let result = serialize_level(&ron, &file_path)
+            .map_err(|e| e.to_string())
+            .and_then(|serialized| std::fs::write(&file_path, serialized).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            log::error!("process_apply_prefab: {}", e);
+
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Error)
+                .set_title("Apply prefab error")
+                .set_description(&format!("Failed to write {}: {}", file_path.display(), e))
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
+
+            continue;
+        }
+
+        commands.add(SetRonAsset { handle: instance.handle.clone(), ron });
+    }
+}
+
+pub(crate) fn process_revert_prefab(
+    mut commands: Commands,
+    mut reader: EventReader<RevertPrefabEvent>,
+    instance_query: Query<(&PrefabInstance, &EditorId, &Transform)>,
+    children_query: Query<&Children>,
+    prefab_query: Query<&PrefabNode>,
+    mut writer: EventWriter<AddRonEvent>,
+) {
+    for RevertPrefabEvent { entity } in reader.iter() {
+        let (instance, id, transform) = if_err_continue!(instance_query.get(*entity));
+
+        log::info!("process_revert_prefab");
+
+        despawn_prefab_nodes(&mut commands, *entity, id, &children_query, &prefab_query);
+
+        writer.send(AddRonEvent {
+            entity: *entity,
+            id: *id,
+            handle: instance.handle.clone(),
+            transform: *transform,
+            overrides: Vec::new(),
+        });
+    }
+}
+
+pub(crate) fn process_create_prefab(
+    mut commands: Commands,
+    mut reader: EventReader<CreatePrefabEvent>,
+    asset_server: Res<AssetServer>,
+    file_state: Res<FileState>,
+    select_state: Res<SelectState>,
+    mut editor_state: ResMut<MyEditorState>,
+    transform_query: Query<&Transform, With<Object>>,
+    parent_query: Query<&Parent>,
+    layer_query: Query<&LevelLayer>,
+    mut pending: Local<Vec<PendingPrefab>>,
+    mut writer: EventWriter<AddObjectEvent>,
+) {
+    for CreatePrefabEvent { entity, path } in reader.iter() {
+        let path = if_none_continue!(file_state.to_asset_path(path));
+
+        log::info!("process_create_prefab {:?}", path);
+
+        pending.push(PendingPrefab {
+            entity: *entity,
+            handle: asset_server.load(file_state.to_load_path(&path)),
+            path,
+        });
+    }
+
+    let mut waiting = Vec::new();
+
+    for prefab in pending.drain(..) {
+        match asset_server.get_load_state(&prefab.handle) {
+            LoadState::Loaded => (),
+            LoadState::Failed => {
+                log::error!("process_create_prefab: failed to load {}", prefab.path.display());
+                continue;
+            },
+            _ => {
+                waiting.push(prefab);
+                continue;
+            },
+        };
+
+        let transform = if_err_continue!(transform_query.get(prefab.entity));
+
+        // The prefab root is saved at the origin, its translation moves to the instance.
+        let transform = Transform::from_translation(transform.translation);
+
+        let instance = commands.spawn_empty().id();
+
+        if let Ok(parent) = parent_query.get(prefab.entity) {
+            commands.entity(parent.get()).add_child(instance);
+        }
+
+        if let Ok(layer) = layer_query.get(prefab.entity) {
+            commands.entity(instance).insert(layer.clone());
+        }
+
+        commands.entity(prefab.entity).despawn_recursive();
+
+        editor_state
+            .objects
+            .insert(prefab.path.display().to_string(), Object {
+                object_type: ObjectType::Ron,
+                path: Some(prefab.path.clone()),
+                ..Default::default()
+            });
+
+        writer.send(AddObjectEvent {
+            entity: Some(instance),
+            id: None,
+            object: Some(Object {
+                object_type: ObjectType::Ron,
+                path: Some(prefab.path),
+                ..Default::default()
+            }),
+            transform: Some(transform),
+            selected: select_state.entity == Some(prefab.entity),
+            overrides: Vec::new(),
+        });
+    }
+
+    *pending = waiting;
+}
+
+// Objects added under the instance in the level are kept.
+pub(crate) fn despawn_prefab_nodes(
+    commands: &mut Commands,
+    entity: Entity,
+    instance: &EditorId,
+    children_query: &Query<&Children>,
+    prefab_query: &Query<&PrefabNode>,
+) {
+    let children = if_err_return!(children_query.get(entity));
+
+    for child in children.iter() {
+        if prefab_query.get(*child).map_or(false, |node| node.instance == *instance) {
+            commands.entity(*child).despawn_recursive();
+        }
+    }
+}
+
+// Watches the prefabs through the asset events, the source watcher of the asset server
+// reports files saved outside of the editor.
+pub(crate) fn process_modified_prefabs(
+    world: &World,
+    mut commands: Commands,
+    mut reader: EventReader<AssetEvent<Ron>>,
+    instance_query: Query<(Entity, &PrefabInstance)>,
+) {
+    for event in reader.iter() {
+        let handle = match event {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+
+        let source = if_none_continue!(world.resource::<PrefabSources>().rons.get(&handle.id()));
+
+        for (entity, instance) in instance_query.iter() {
+            if instance.handle != *handle {
+                continue;
+            }
+
+            log::info!("process_modified_prefabs {:?}", entity);
+
+            commands.entity(entity).insert(RebuildPrefab {
+                overrides: collect_overrides_from(world, entity, source),
+            });
+        }
+    }
+}
+
+// The instance root is kept with its transform, a selected node of the instance
+// is selected again by its id once it is spawned.
+pub(crate) fn process_rebuild_prefabs(
+    mut commands: Commands,
+    rebuild_query: Query<(Entity, &RebuildPrefab, &PrefabInstance, &EditorId, &Transform)>,
+    children_query: Query<&Children>,
+    prefab_query: Query<&PrefabNode>,
+    parent_query: Query<&Parent>,
+    id_query: Query<&EditorId>,
+    id_map: Res<EditorIdMap>,
+    mut select_state: ResMut<SelectState>,
+    mut pending_select: Local<Option<EditorId>>,
+    mut picking_writer: EventWriter<PickingEvent>,
+    mut writer: EventWriter<AddRonEvent>,
+) {
+    if let Some(id) = *pending_select {
+        if let Some(entity) = id_map.get(&id) {
+            picking_writer.send(PickingEvent { entity });
+            *pending_select = None;
+        }
+    }
+
+    for (entity, rebuild, instance, id, transform) in rebuild_query.iter() {
+        if let Some(selected) = select_state.entity {
+            if selected != entity && parent_query.iter_ancestors(selected).any(|ancestor| ancestor == entity) {
+                *pending_select = id_query.get(selected).ok().copied();
+                select_state.entity = None;
+            }
+        }
+
+        despawn_prefab_nodes(&mut commands, entity, id, &children_query, &prefab_query);
+
+        commands.entity(entity).remove::<RebuildPrefab>();
+
+        writer.send(AddRonEvent {
+            entity,
+            id: *id,
+            handle: instance.handle.clone(),
+            transform: *transform,
+            overrides: rebuild.overrides.clone(),
+        });
+    }
+}
+
+// The instance the entity belongs to, the entity itself for an instance root.
+pub fn prefab_instance_entity(
+    entity: Entity,
+    instance_query: &Query<&PrefabInstance>,
+    prefab_query: &Query<&PrefabNode>,
+    id_map: &crate::objects::EditorIdMap,
+) -> Option<Entity> {
+    if instance_query.contains(entity) {
+        return Some(entity);
+    }
+
+    let node = prefab_query.get(entity).ok()?;
+    id_map.get(&node.instance)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::objects::{EditorId, Object, ObjectType};
+    use crate::objects::ron::{Ron, RonNode, RonOverride, RonTransform, RON_VERSION};
+
+    fn node(id: EditorId, object: usize, childrens: Vec<EditorId>) -> RonNode {
+        RonNode {
+            id,
+            transform: RonTransform::default(),
+            object,
+            has_parent: false,
+            childrens,
+            components: Vec::new(),
+            overrides: Vec::new(),
+            layer: None,
+        }
+    }
+
+    #[test]
+    fn apply_overrides_drops_out_of_range_nodes() {
+        let (root, broken, child) = (EditorId::default(), EditorId::default(), EditorId::default());
+
+        let mut ron = Ron {
+            version: RON_VERSION,
+            objects: vec![Object { object_type: ObjectType::Empty, ..Default::default() }],
+            nodes: vec![
+                node(root, 0, vec![broken, child]),
+                node(broken, 7, Vec::new()),
+                node(child, 0, Vec::new()),
+            ],
+            ..Default::default()
+        };
+
+        let light = Object { object_type: ObjectType::Light, ..Default::default() };
+
+        ron.apply_overrides(&[RonOverride {
+            node: child,
+            object: Some(light.clone()),
+            ..Default::default()
+        }]);
+
+        assert_eq!(ron.nodes.len(), 2);
+        assert!(ron.nodes.iter().all(|node| node.id != broken));
+        assert_eq!(ron.nodes[0].childrens, vec![child]);
+        assert_eq!(ron.objects[ron.nodes[1].object], light);
+        assert!(ron.nodes.iter().all(|node| node.object < ron.objects.len()));
+    }
+}
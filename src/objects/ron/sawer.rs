@@ -1,35 +1,60 @@
+use std::path::Path;
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 use bevy::log;
 use bevy::reflect::serde::ReflectSerializer;
 
-use crate::objects::{RonNode, Ron};
+use crate::objects::{RonNode, Ron, RonError, RON_VERSION, EditorId};
 use crate::gui::FileState;
 
 use crate::{if_err_return, if_none_continue};
 
 use super::SaveRonEvent;
 use super::Object;
+use super::components::collect_components;
+use super::autosave::write_snapshot;
+use super::binary::serialize_level;
+use super::prefab::{collect_overrides, PrefabNode};
+use super::layers::{LevelLayers, RonLayer, world_layer};
+use super::settings::LevelSettings;
 
 
-pub fn process_save_ron (
+pub fn process_save_ron_path (
     mut state: ResMut<FileState>,
+    mut layers: ResMut<LevelLayers>,
+    mut reader: EventReader<SaveRonEvent>,
+) {
+    for SaveRonEvent {path, root, snapshot } in reader.iter() {
+        if root.is_none() && path.is_some() && !snapshot {
+            state.current_file_path = path.clone();
+
+            if let Some(path) = path {
+                layers.saved(path);
+            }
+        }
+    }
+}
+
+// Reads the whole world to collect the saved components of every node.
+pub fn process_save_ron (
+    world: &World,
     mut reader: EventReader<SaveRonEvent>,
-    object_query: Query<(Entity, &Transform, &Object)>,
+    object_query: Query<(Entity, &Transform, &Object, &EditorId)>,
     children_query: Query<&Children, With<Object>>,
     parent_query: Query<&Parent, With<Object>>,
 ) {
-    for SaveRonEvent {path, root } in reader.iter() {
+    for SaveRonEvent {path, root, snapshot } in reader.iter() {
 
         let path_buf = if_none_continue!(path);
 
         log::info!("process_save_ron");
 
         let mut objects = HashMap::new();
-        let mut nodes = HashMap::new();
+        let mut nodes = Vec::new();
 
         if let Some(root) = root {
             process_save_node (
+                world,
                 root,
                 &object_query,
                 &children_query,
@@ -37,84 +62,228 @@ pub fn process_save_ron (
                 &mut objects,
                 &mut nodes,
             );
-        } else {  
-            state.current_file_path = path.clone(); 
-              
-            for (_, _, object_type) in object_query.iter() {
-                if !objects.contains_key(object_type) {
-                    objects.insert(object_type.clone(), objects.len());
-                }
+
+            // The subtree is saved as a prefab with the pivot on its root.
+            if let Some(node) = nodes.first_mut() {
+                node.has_parent = false;
+                node.transform.translation = [0., 0., 0.];
             }
 
-            for (entity, transform, object_type) in object_query.iter() {
-                let node = RonNode {
-                    transform: transform.compute_matrix().to_cols_array(),
-                    object: *objects.get(object_type).expect("Failed to create node"),
-                    has_parent: parent_query.contains(entity),
-                    childrens: Vec::new(),
-                };
+            let ron = create_ron(objects, nodes, Vec::new());
 
-                nodes.insert(entity, (node, nodes.len()));
-            }
+            write_level(&ron, path_buf);
+            continue;
         }
-        
-        let mut ron = Ron {
-            objects: objects.into_iter().map(|(v, k)| (k, v)).collect(),
-            nodes: nodes.iter().map(|(_, (v, k))| (*k, v.clone())).collect(),
-        };
-
-        for (entity,  (_, key) ) in &nodes {
-            if let Ok(children) = children_query.get(*entity) { 
-                let node = ron.nodes.get_mut(key).expect("Failed process create add childs");
-                for child in children.iter() {  
-                    if object_query.contains(*child) {
-                        node.childrens.push(nodes.get(child).expect("Failed to add child to node").1);
-                    }
-                }
+
+        // Snapshots flatten the layers into one file, so they can be restored from anywhere.
+        // The root nodes keep the name of their layer.
+        if *snapshot {
+            let mut ron = create_level(world, |_| true, &object_query, &children_query, &parent_query, Vec::new());
+            ron.settings = Some(world.resource::<LevelSettings>().clone());
+            ron.snapshot_layers = Some(world.resource::<LevelLayers>().to_snapshot());
+
+            let entities: HashMap<EditorId, Entity> = object_query
+                .iter()
+                .map(|(entity, _, _, id)| (*id, entity))
+                .collect();
+
+            for node in ron.nodes.iter_mut().filter(|node| !node.has_parent) {
+                node.layer = entities
+                    .get(&node.id)
+                    .and_then(|entity| world_layer(world, *entity))
+                    .map(|layer| layer.name.clone());
             }
+
+            write_snapshot(world, path_buf, &serialize_ron(&ron).expect("Failed to serialize level"));
+            continue;
         }
 
-        let mut registry = bevy::reflect::TypeRegistryInternal::new();
-        registry.register::<Ron>();
-        
-        let serializer = ReflectSerializer::new(&ron, &registry);
-        let serialized: String = ron::ser::to_string_pretty(&serializer, ron::ser::PrettyConfig::default())
-            .expect("Failed to serialize level");
+        let layers = world.resource::<LevelLayers>();
 
-        let assets_path = state.assets_path.to_str().expect("serialize error: can't replace exe path").replace("\\","\\\\");
-        let serialized = serialized.replace(&assets_path, "__path_to_assets__"); 
+        for layer in layers.layers.iter().filter(|layer| layer.is_loaded()) {
+            let ron = create_level(
+                world,
+                |entity| world_layer(world, entity).map_or(false, |level_layer| level_layer.name == layer.name),
+                &object_query,
+                &children_query,
+                &parent_query,
+                Vec::new(),
+            );
+
+            write_level(&ron, &layers.file_path(layer, path_buf));
+        }
 
-        std::fs::write(path_buf, serialized).unwrap_or_else(|e| error!("Failed to write level {}", e));
+        let mut ron = create_level(
+            world,
+            |entity| world_layer(world, entity).is_none(),
+            &object_query,
+            &children_query,
+            &parent_query,
+            layers.to_ron(path_buf),
+        );
+        ron.settings = Some(world.resource::<LevelSettings>().clone());
+
+        write_level(&ron, path_buf);
     }
 }
 
-fn process_save_node (
-    entity: &Entity,
-    object_query: &Query<(Entity, &Transform, &Object)>,
+fn create_level (
+    world: &World,
+    filter: impl Fn(Entity) -> bool,
+    object_query: &Query<(Entity, &Transform, &Object, &EditorId)>,
     children_query: &Query<&Children, With<Object>>,
     parent_query: &Query<&Parent, With<Object>>,
-    objects: &mut HashMap<Object, usize>,
-    nodes: &mut HashMap<Entity, (RonNode, usize)>,
-) {
-    let (entity, transform, object_type) = if_err_return!(object_query.get(*entity));
+    layers: Vec<RonLayer>,
+) -> Ron {
+    let mut objects = HashMap::new();
+    let mut nodes = Vec::new();
+
+    for (entity, transform, object_type, id) in object_query.iter() {
+        // Spawned from a nested ron, saved as overrides of the instance.
+        if world.get::<PrefabNode>(entity).is_some() || !filter(entity) {
+            continue;
+        }
+
+        let mut node = create_node(
+            world,
+            entity,
+            transform,
+            object_type,
+            id,
+            object_query,
+            children_query,
+            parent_query,
+            &mut objects,
+        );
+
+        // No saved node lists the children of a prefab node, they are saved as root objects.
+        let parent_is_prefab = parent_query
+            .get(entity)
+            .map_or(false, |parent| world.get::<PrefabNode>(parent.get()).is_some());
+
+        if parent_is_prefab {
+            log::warn!("create_level: {:?} is a child of a prefab node, saved as a root object", id);
+
+            if let Some(global_transform) = world.get::<GlobalTransform>(entity) {
+                node.transform = (&global_transform.compute_transform()).into();
+            }
+            node.has_parent = false;
+        }
+
+        nodes.push(node);
+    }
 
+    create_ron(objects, nodes, layers)
+}
+
+fn create_ron(objects: HashMap<Object, usize>, nodes: Vec<RonNode>, layers: Vec<RonLayer>) -> Ron {
+    let mut objects: Vec<(Object, usize)> = objects.into_iter().collect();
+    objects.sort_by_key(|(_, key)| *key);
+
+    let mut ron = Ron {
+        version: RON_VERSION,
+        objects: objects.into_iter().map(|(object, _)| object).collect(),
+        nodes,
+        layers,
+        settings: None,
+        snapshot_layers: None,
+    };
+
+    ron.sort();
+    ron
+}
+
+fn write_level(ron: &Ron, path: &Path) {
+    let serialized = serialize_level(ron, path).expect("Failed to serialize level");
+
+    std::fs::write(path, serialized).unwrap_or_else(|e| error!("Failed to write level {}", e));
+}
+
+pub fn pretty_config() -> ron::ser::PrettyConfig {
+    ron::ser::PrettyConfig::default()
+        .new_line("\n".to_string())
+}
+
+pub fn serialize_ron(ron: &Ron) -> Result<String, RonError> {
+    let mut registry = bevy::reflect::TypeRegistryInternal::new();
+    registry.register::<Ron>();
+
+    let serializer = ReflectSerializer::new(ron, &registry);
+
+    Ok(ron::ser::to_string_pretty(&serializer, pretty_config())?)
+}
+
+fn create_node (
+    world: &World,
+    entity: Entity,
+    transform: &Transform,
+    object_type: &Object,
+    id: &EditorId,
+    object_query: &Query<(Entity, &Transform, &Object, &EditorId)>,
+    children_query: &Query<&Children, With<Object>>,
+    parent_query: &Query<&Parent, With<Object>>,
+    objects: &mut HashMap<Object, usize>,
+) -> RonNode {
     if !objects.contains_key(object_type) {
         objects.insert(object_type.clone(), objects.len());
     }
 
-    let node = RonNode {
-        transform: transform.compute_matrix().to_cols_array(),
+    let mut childrens = Vec::new();
+
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter() {
+            if world.get::<PrefabNode>(*child).is_some() {
+                continue;
+            }
+
+            if let Ok((_, _, _, child_id)) = object_query.get(*child) {
+                childrens.push(*child_id);
+            }
+        }
+    }
+
+    RonNode {
+        id: *id,
+        transform: transform.into(),
         object: *objects.get(object_type).expect("Failed to create node"),
         has_parent: parent_query.contains(entity),
-        childrens: Vec::new(),
-    };
+        childrens,
+        components: collect_components(world, entity),
+        overrides: collect_overrides(world, entity),
+        layer: None,
+    }
+}
+
+fn process_save_node (
+    world: &World,
+    entity: &Entity,
+    object_query: &Query<(Entity, &Transform, &Object, &EditorId)>,
+    children_query: &Query<&Children, With<Object>>,
+    parent_query: &Query<&Parent, With<Object>>,
+    objects: &mut HashMap<Object, usize>,
+    nodes: &mut Vec<RonNode>,
+) {
+    let (entity, transform, object_type, id) = if_err_return!(object_query.get(*entity));
+
+    let node = create_node(
+        world,
+        entity,
+        transform,
+        object_type,
+        id,
+        object_query,
+        children_query,
+        parent_query,
+        objects,
+    );
 
-    nodes.insert(entity, (node, nodes.len()));
+    nodes.push(node);
 
     if let Ok(children) = children_query.get(entity) { 
         for child in children.iter() {  
-            if object_query.contains(*child) {
+            if object_query.contains(*child) && world.get::<PrefabNode>(*child).is_none() {
                 process_save_node (
+                    world,
                     child,
                     object_query,
                     children_query,
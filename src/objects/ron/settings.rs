@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::RapierConfiguration;
+use ::serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+// World settings of a master level, applied when the level is loaded.
+// Levels saved without them get the editor defaults.
+#[derive(Debug, Clone, PartialEq, Resource, Reflect, FromReflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelSettings {
+    pub author: String,
+    pub description: String,
+    pub ambient_color: [f32; 4],
+    pub ambient_brightness: f32,
+    pub clear_color: [f32; 4],
+    pub gravity: [f32; 3],
+}
+
+impl Default for LevelSettings {
+    fn default() -> Self {
+        Self {
+            author: String::new(),
+            description: String::new(),
+            ambient_color: [1., 1., 1., 1.],
+            ambient_brightness: 0.5,
+            clear_color: [0.4, 0.4, 0.4, 1.],
+            gravity: [0., -9.81, 0.],
+        }
+    }
+}
+
+impl LevelSettings {
+    pub fn ambient_light(&self) -> AmbientLight {
+        AmbientLight {
+            color: Color::rgba(self.ambient_color[0], self.ambient_color[1], self.ambient_color[2], self.ambient_color[3]),
+            brightness: self.ambient_brightness,
+        }
+    }
+
+    pub fn clear_color(&self) -> ClearColor {
+        ClearColor(Color::rgba(self.clear_color[0], self.clear_color[1], self.clear_color[2], self.clear_color[3]))
+    }
+
+    pub fn gravity(&self) -> Vec3 {
+        Vec3::from_array(self.gravity)
+    }
+}
+
+pub(super) fn serialize_settings<S: Serializer>(settings: &Option<LevelSettings>, serializer: S) -> Result<S::Ok, S::Error> {
+    match settings {
+        Some(settings) => settings.serialize(serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub(super) fn deserialize_settings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<LevelSettings>, D::Error> {
+    LevelSettings::deserialize(deserializer).map(Some)
+}
+
+// Also runs at startup, when the resource is added.
+pub(crate) fn apply_level_settings(
+    mut commands: Commands,
+    settings: Res<LevelSettings>,
+    rapier_config: Option<ResMut<RapierConfiguration>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    commands.insert_resource(settings.ambient_light());
+    commands.insert_resource(settings.clear_color());
+
+    if let Some(mut rapier_config) = rapier_config {
+        rapier_config.gravity = settings.gravity();
+    }
+}
@@ -1,64 +1,129 @@
-use bevy::{log, prelude::*};
+use bevy::{log, prelude::*, utils::{HashMap, HashSet}};
 use crate::{if_none_continue, if_none_return};
-use crate::objects::{SpawnRonEvent, AddObjectEvent, ObjectType, Object};
-use super::{Ron, AddRonEvent, RonNode};
+use std::path::{Path, PathBuf};
+use crate::objects::{SpawnRonEvent, AddObjectEvent, EditorId, Object, LoadErrorEvent};
+use super::{Ron, AddRonEvent, RonNode, RonOverride, PrefabInstance, PrefabNode};
+use super::components::InsertRonComponents;
+use super::prefab::PrefabSources;
+use super::layers::{LevelLayer, LevelLayers};
+use super::settings::LevelSettings;
 
 pub fn process_spawn_ron (
     mut commands: Commands,
     mut reader: EventReader<SpawnRonEvent>,
     ron_assets: Res<Assets<Ron>>,
+    asset_server: Res<AssetServer>,
+    mut layers: ResMut<LevelLayers>,
+    mut settings: ResMut<LevelSettings>,
     mut writer: EventWriter<AddObjectEvent>,
+    mut error_writer: EventWriter<LoadErrorEvent>,
 ) {
  //   log::info!("process_spawn_ron");
 
     let SpawnRonEvent { handle } = if_none_return!(reader.iter().last());
 
     let ron = if_none_return!(ron_assets.get(&handle));
+    let file = asset_server.get_handle_path(handle).map(|path| path.path().to_path_buf());
 
-    for (_, node) in ron.nodes.iter() {
+    let roots = spawn_roots(&mut commands, ron, &file, &mut writer, &mut error_writer);
+
+    match ron.snapshot_layers.as_ref() {
+        Some(snapshot_layers) => {
+            layers.restore(snapshot_layers);
+
+            let root_nodes = ron.nodes.iter().filter(|node| !node.has_parent);
+
+            for (entity, node) in roots.into_iter().zip(root_nodes) {
+                if let Some(name) = node.layer.as_ref() {
+                    commands.entity(entity).insert(LevelLayer { name: name.clone() });
+                }
+            }
+        },
+        None => layers.load(&ron.layers, file.as_deref().unwrap_or(Path::new("")), &asset_server),
+    };
+
+    // Layers and prefabs opened as a level keep the current settings.
+    if let Some(level_settings) = ron.settings.as_ref() {
+        *settings = level_settings.clone();
+    }
+}
+
+// Spawns the nodes of a level, returns the root entities.
+pub(super) fn spawn_roots(
+    commands: &mut Commands,
+    ron: &Ron,
+    file: &Option<PathBuf>,
+    writer: &mut EventWriter<AddObjectEvent>,
+    error_writer: &mut EventWriter<LoadErrorEvent>,
+) -> Vec<Entity> {
+    let nodes = ron.nodes.iter().map(|node| (node.id, node)).collect();
+    let mut visited = HashSet::new();
+
+    let mut roots = Vec::new();
+
+    for node in ron.nodes.iter() {
         if node.has_parent {
             continue;
         }
 
-        process_spawn_node(    
-            &mut commands,
+        roots.push(process_spawn_node(
+            commands,
+            None,
             None,
             node,
+            &nodes,
+            &HashMap::new(),
+            &mut visited,
             &ron,
-            &mut writer,
-        );
+            file,
+            writer,
+            error_writer,
+        ));
     }
+
+    roots
 }
 
-pub fn process_add_ron (
+pub(crate) fn process_add_ron (
     mut commands: Commands,
     mut reader: EventReader<AddRonEvent>,
     ron_assets: Res<Assets<Ron>>,
+    mut sources: ResMut<PrefabSources>,
+    asset_server: Res<AssetServer>,
     mut add_obj_writer: EventWriter<AddObjectEvent>,
+    mut error_writer: EventWriter<LoadErrorEvent>,
 //    mut set_pickable_writer: EventWriter<ProcessNewMeshEvent>,
 ) {  
     for AddRonEvent {
         entity,
+        id,
         handle,
         transform,
+        overrides,
     } in reader.iter() {
 
     // log::info!("process_add_ron");
 
         let ron = if_none_continue!(ron_assets.get(handle));
 
-        add_obj_writer.send( AddObjectEvent{ 
-            entity: Some(*entity), 
-            object: Some( Object {
-                object_type: ObjectType::Empty,
-                path: None,
-                collider: None,
-            }), 
-            transform: Some(*transform),
-            selected: true,
-        } );
-
-        for (_, node) in ron.nodes.iter() {
+        sources.insert(handle, ron);
+
+        let file = asset_server.get_handle_path(handle).map(|path| path.path().to_path_buf());
+
+        // The root keeps its ron object, so the instance is saved as a reference to the file.
+        commands.entity(*entity).insert((
+            SpatialBundle {
+                transform: *transform,
+                ..Default::default()
+            },
+            PrefabInstance { handle: handle.clone() },
+        ));
+
+        let nodes = ron.nodes.iter().map(|node| (node.id, node)).collect();
+        let overrides = overrides.iter().map(|ron_override| (ron_override.node, ron_override)).collect();
+        let mut visited = HashSet::new();
+
+        for node in ron.nodes.iter() {
             if node.has_parent {
                 continue;
             }
@@ -66,9 +131,15 @@ pub fn process_add_ron (
             process_spawn_node(    
                 &mut commands,
                 Some(*entity),
+                Some(*id),
                 node,
+                &nodes,
+                &overrides,
+                &mut visited,
                 &ron,
+                &file,
                 &mut add_obj_writer,
+                &mut error_writer,
             );
         }
 
@@ -76,33 +147,158 @@ pub fn process_add_ron (
     }
 }
 
+// `instance` is the id of the nested ron object the node is spawned under,
+// node ids are combined with it so that every instance gets its own ids.
+// `visited` are the nodes spawned so far, a node listed again is not spawned twice.
 fn process_spawn_node(
     commands: &mut Commands,
     parent: Option<Entity>,
+    instance: Option<EditorId>,
     node: &RonNode,
+    nodes: &HashMap<EditorId, &RonNode>,
+    overrides: &HashMap<EditorId, &RonOverride>,
+    visited: &mut HashSet<EditorId>,
     ron: &Ron,
+    file: &Option<PathBuf>,
     writer: &mut EventWriter<AddObjectEvent>,
-) { 
+    error_writer: &mut EventWriter<LoadErrorEvent>,
+) -> Entity {
+    visited.insert(node.id);
+
     let entity = commands.spawn_empty().id();
 
     if let Some(parent) = parent {
         commands.entity(parent).add_child(entity);
     }
 
+    // A broken node is spawned empty, so that its children are still loaded.
+    let (transform, object, components) = match node.with_override(overrides.get(&node.id).copied(), ron) {
+        Some((transform, object, components)) => {
+            if let Some(reason) = object.missing_data() {
+                error_writer.send(LoadErrorEvent::new(file.clone(), Some(node.id), reason));
+            }
+
+            (transform, object, components)
+        },
+        None => {
+            error_writer.send(LoadErrorEvent::new(
+                file.clone(),
+                Some(node.id),
+                format!("object index {} is out of range", node.object),
+            ));
+
+            ((&node.transform).into(), Object::default(), node.components.clone())
+        },
+    };
+
+    if !components.is_empty() {
+        commands.add(InsertRonComponents {
+            entity,
+            components,
+        });
+    }
+
+    let id = if let Some(instance) = instance {
+        commands.entity(entity).insert(PrefabNode { instance, node: node.id });
+        instance.combine(&node.id)
+    } else {
+        node.id
+    };
+
     writer.send( AddObjectEvent{ 
         entity: Some(entity), 
-        object: Some(ron.objects.get(&node.object).expect("process_load_ron err: failed create the node").clone()), 
-        transform: Some(Transform::from_matrix(Mat4::from_cols_array(&node.transform))),
+        id: Some(id),
+        object: Some(object),
+        transform: Some(transform),
         selected: false,
+        overrides: node.overrides.clone(),
     } );
 
     for node_id in node.childrens.iter() { 
+        let child = match nodes.get(node_id) {
+            Some(child) => child,
+            None => {
+                error_writer.send(LoadErrorEvent::new(
+                    file.clone(),
+                    Some(node.id),
+                    format!("child node {} is missing", node_id.0),
+                ));
+                continue;
+            },
+        };
+
+        if visited.contains(node_id) {
+            error_writer.send(LoadErrorEvent::new(file.clone(), Some(node.id), "cyclic child reference"));
+            continue;
+        }
+
         process_spawn_node(
             commands, 
             Some(entity), 
-            ron.nodes.get(&node_id).expect("process_load_ron err: failed create the node"), 
+            instance,
+            child,
+            nodes,
+            overrides,
+            visited,
             &ron,
+            file,
             writer,
+            error_writer,
         );
     }        
+
+    entity
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+    use bevy::prelude::*;
+    use crate::objects::{AddObjectEvent, EditorId, LoadErrorEvent, Object, ObjectType};
+    use crate::objects::ron::{Ron, RonNode, RonTransform, RON_VERSION};
+    use super::spawn_roots;
+
+    fn node(id: EditorId, has_parent: bool, childrens: Vec<EditorId>) -> RonNode {
+        RonNode {
+            id,
+            transform: RonTransform::default(),
+            object: 0,
+            has_parent,
+            childrens,
+            components: Vec::new(),
+            overrides: Vec::new(),
+            layer: None,
+        }
+    }
+
+    #[test]
+    fn cyclic_children_are_spawned_once() {
+        let (a, b) = (EditorId::default(), EditorId::default());
+
+        let ron = Ron {
+            version: RON_VERSION,
+            objects: vec![Object { object_type: ObjectType::Empty, ..Default::default() }],
+            nodes: vec![node(a, false, vec![b]), node(b, true, vec![a])],
+            ..Default::default()
+        };
+
+        let mut world = World::new();
+        world.init_resource::<Events<AddObjectEvent>>();
+        world.init_resource::<Events<LoadErrorEvent>>();
+
+        let mut state: SystemState<(Commands, EventWriter<AddObjectEvent>, EventWriter<LoadErrorEvent>)> = SystemState::new(&mut world);
+        let (mut commands, mut writer, mut error_writer) = state.get_mut(&mut world);
+
+        let roots = spawn_roots(&mut commands, &ron, &None, &mut writer, &mut error_writer);
+        state.apply(&mut world);
+
+        assert_eq!(roots.len(), 1);
+        assert_eq!(world.resource::<Events<AddObjectEvent>>().len(), 2);
+
+        let errors: Vec<&LoadErrorEvent> = world.resource::<Events<LoadErrorEvent>>().iter_current_update_events().collect();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].node, Some(b));
+        assert_eq!(errors[0].reason, "cyclic child reference");
+    }
 }
@@ -0,0 +1,240 @@
+use std::fmt;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::objects::{EditorId, ObjectType};
+
+use super::{Ron, RonNode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RonIssue {
+    DuplicateNode(EditorId),
+    DanglingObject { node: EditorId, object: usize },
+    MissingChild { node: EditorId, child: EditorId },
+    ChildCycle { node: EditorId, child: EditorId },
+    OrphanNode(EditorId),
+    MissingPath { object: usize },
+    UnusedObject { object: usize },
+}
+
+impl RonIssue {
+    // Unused objects don't break loading.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, RonIssue::UnusedObject { .. })
+    }
+}
+
+impl fmt::Display for RonIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonIssue::DuplicateNode(id) => write!(f, "node {} is defined more than once", id.0),
+            RonIssue::DanglingObject { node, object } => write!(f, "node {} refers to missing object {}", node.0, object),
+            RonIssue::MissingChild { node, child } => write!(f, "node {} refers to missing child {}", node.0, child.0),
+            RonIssue::ChildCycle { node, child } => write!(f, "node {} lists its ancestor {} as a child", node.0, child.0),
+            RonIssue::OrphanNode(id) => write!(f, "node {} has a parent but no node lists it as a child", id.0),
+            RonIssue::MissingPath { object } => write!(f, "object {} has no path", object),
+            RonIssue::UnusedObject { object } => write!(f, "object {} is not used by any node", object),
+        }
+    }
+}
+
+impl Ron {
+    // Checks references inside the level, asset paths are checked by the caller.
+    pub fn validate(&self) -> Vec<RonIssue> {
+        let mut issues = Vec::new();
+
+        let mut ids = HashSet::new();
+
+        for node in self.nodes.iter() {
+            if !ids.insert(node.id) {
+                issues.push(RonIssue::DuplicateNode(node.id));
+            }
+        }
+
+        let mut used = HashSet::new();
+
+        for node in self.nodes.iter() {
+            if node.object < self.objects.len() {
+                used.insert(node.object);
+            } else {
+                issues.push(RonIssue::DanglingObject { node: node.id, object: node.object });
+            }
+
+            for child in node.childrens.iter() {
+                if !ids.contains(child) {
+                    issues.push(RonIssue::MissingChild { node: node.id, child: *child });
+                }
+            }
+        }
+
+        let childrens: HashSet<EditorId> = self.nodes
+            .iter()
+            .flat_map(|node| node.childrens.iter().copied())
+            .collect();
+
+        // Not reached by the spawner.
+        for node in self.nodes.iter() {
+            if node.has_parent && !childrens.contains(&node.id) {
+                issues.push(RonIssue::OrphanNode(node.id));
+            }
+        }
+
+        issues.append(&mut self.child_cycles());
+
+        for (index, object) in self.objects.iter().enumerate() {
+            match object.object_type {
+                ObjectType::Scene | ObjectType::Mesh | ObjectType::Node | ObjectType::Ron => {
+                    if object.path.is_none() {
+                        issues.push(RonIssue::MissingPath { object: index });
+                    }
+                },
+                _ => (),
+            };
+
+            if !used.contains(&index) {
+                issues.push(RonIssue::UnusedObject { object: index });
+            }
+        }
+
+        issues
+    }
+
+    // A depth first walk without recursion, every child that is on the current path closes a cycle.
+    fn child_cycles(&self) -> Vec<RonIssue> {
+        let mut issues = Vec::new();
+
+        let nodes: HashMap<EditorId, &RonNode> = self.nodes.iter().map(|node| (node.id, node)).collect();
+        let mut done = HashSet::new();
+
+        for start in self.nodes.iter() {
+            if done.contains(&start.id) {
+                continue;
+            }
+
+            // Nodes of the current path with the index of their next child.
+            let mut path: Vec<(EditorId, usize)> = vec![(start.id, 0)];
+            let mut on_path = HashSet::new();
+            on_path.insert(start.id);
+
+            while let Some(&(id, index)) = path.last() {
+                let child = nodes.get(&id).and_then(|node| node.childrens.get(index)).copied();
+
+                match child {
+                    Some(child) => {
+                        if let Some(last) = path.last_mut() {
+                            last.1 += 1;
+                        }
+
+                        if on_path.contains(&child) {
+                            issues.push(RonIssue::ChildCycle { node: id, child });
+                        } else if !done.contains(&child) && nodes.contains_key(&child) {
+                            path.push((child, 0));
+                            on_path.insert(child);
+                        }
+                    },
+                    None => {
+                        path.pop();
+                        on_path.remove(&id);
+                        done.insert(id);
+                    },
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::objects::{EditorId, Object, ObjectType};
+    use crate::objects::ron::{Ron, RonIssue, RonNode, RonTransform, RON_VERSION};
+
+    fn node(id: EditorId, object: usize, has_parent: bool, childrens: Vec<EditorId>) -> RonNode {
+        RonNode {
+            id,
+            transform: RonTransform::default(),
+            object,
+            has_parent,
+            childrens,
+            components: Vec::new(),
+            overrides: Vec::new(),
+            layer: None,
+        }
+    }
+
+    fn level() -> (Ron, EditorId, EditorId) {
+        let (root, child) = (EditorId::default(), EditorId::default());
+
+        let ron = Ron {
+            version: RON_VERSION,
+            objects: vec![
+                Object { object_type: ObjectType::Empty, ..Default::default() },
+                Object {
+                    object_type: ObjectType::Scene,
+                    path: Some(PathBuf::from("gltf/ground_4/scene.gltf")),
+                    ..Default::default()
+                },
+            ],
+            nodes: vec![
+                node(root, 0, false, vec![child]),
+                node(child, 1, true, Vec::new()),
+            ],
+            ..Default::default()
+        };
+
+        (ron, root, child)
+    }
+
+    #[test]
+    fn clean_level_has_no_issues() {
+        let (ron, _, _) = level();
+
+        assert_eq!(ron.validate(), Vec::new());
+    }
+
+    #[test]
+    fn dangling_object_is_reported() {
+        let (mut ron, _, child) = level();
+        ron.nodes[1].object = 5;
+
+        let issues = ron.validate();
+
+        assert!(issues.contains(&RonIssue::DanglingObject { node: child, object: 5 }));
+        assert!(issues.contains(&RonIssue::UnusedObject { object: 1 }));
+        assert!(issues.iter().any(RonIssue::is_error));
+    }
+
+    #[test]
+    fn child_cycles_are_reported() {
+        let (mut ron, root, child) = level();
+        ron.nodes[1].childrens.push(root);
+
+        assert_eq!(ron.validate(), vec![RonIssue::ChildCycle { node: child, child: root }]);
+
+        let (mut ron, root, _) = level();
+        ron.nodes[0].childrens.push(root);
+
+        assert_eq!(ron.validate(), vec![RonIssue::ChildCycle { node: root, child: root }]);
+    }
+
+    #[test]
+    fn orphan_nodes_are_reported() {
+        let (mut ron, _, child) = level();
+        ron.nodes[0].childrens.clear();
+
+        let issues = ron.validate();
+
+        assert_eq!(issues, vec![RonIssue::OrphanNode(child)]);
+        assert!(issues[0].is_error());
+    }
+
+    #[test]
+    fn missing_child_is_reported() {
+        let (mut ron, root, _) = level();
+        let missing = EditorId::default();
+        ron.nodes[0].childrens.push(missing);
+
+        assert_eq!(ron.validate(), vec![RonIssue::MissingChild { node: root, child: missing }]);
+    }
+}
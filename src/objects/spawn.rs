@@ -13,10 +13,19 @@ pub struct CompositeObjectLabel;
 pub(crate) struct Resources {
 //    pub mesh: Option<Handle<Mesh>>,
     pub material: Option<Handle<StandardMaterial>>,
+    // Shown for objects with a missing file.
+    pub placeholder_mesh: Option<Handle<Mesh>>,
+    pub placeholder_material: Option<Handle<StandardMaterial>>,
+    pub light_icon_mesh: Option<Handle<Mesh>>,
+    pub light_icon_material: Option<Handle<StandardMaterial>>,
+    pub primitive_material: Option<Handle<StandardMaterial>>,
+    pub emitter_icon_mesh: Option<Handle<Mesh>>,
+    pub emitter_icon_material: Option<Handle<StandardMaterial>>,
 }
 
 pub(crate) fn setup_spawn_resources(
     mut resources: ResMut<Resources>,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     resources.material = Some(materials.add(StandardMaterial {
@@ -24,9 +33,38 @@ pub(crate) fn setup_spawn_resources(
         alpha_mode: AlphaMode::Blend,
         ..default()
     }));
+
+    resources.placeholder_mesh = Some(meshes.add(Mesh::from(shape::Cube { size: 0.5 })));
+
+    resources.placeholder_material = Some(materials.add(StandardMaterial {
+        base_color: Color::rgb(1.0, 0.0, 1.0),
+        unlit: true,
+        ..default()
+    }));
+
+    resources.light_icon_mesh = Some(meshes.add(Mesh::from(shape::UVSphere { radius: 0.15, ..default() })));
+
+    resources.light_icon_material = Some(materials.add(StandardMaterial {
+        base_color: Color::rgb(1.0, 0.9, 0.3),
+        unlit: true,
+        ..default()
+    }));
+
+    resources.primitive_material = Some(materials.add(StandardMaterial {
+        base_color: Color::rgb(0.8, 0.8, 0.8),
+        ..default()
+    }));
+
+    resources.emitter_icon_mesh = Some(meshes.add(Mesh::from(shape::Cube { size: 0.25 })));
+
+    resources.emitter_icon_material = Some(materials.add(StandardMaterial {
+        base_color: Color::rgb(0.3, 0.8, 1.0),
+        unlit: true,
+        ..default()
+    }));
 }
 
-const HIGHLIGHT_TINT: Highlight<StandardMaterial> = Highlight {
+pub(crate) const HIGHLIGHT_TINT: Highlight<StandardMaterial> = Highlight {
     hovered: Some(HighlightKind::new_dynamic(|matl| StandardMaterial {
         base_color: matl.base_color + vec4(0.2, 0.0, 0.0, 0.1), 
         alpha_mode: AlphaMode::Blend,
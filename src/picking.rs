@@ -5,7 +5,7 @@ use bevy_mod_picking::*;
 use bevy_mod_raycast::{RaycastSource, RaycastSystem};
 
 use crate::gui::SelectState;
-use crate::objects::{ObjectType, Object};
+use crate::objects::{ObjectType, Object, LevelLayer, LevelLayers, entity_layer};
 
 #[derive(Clone, Reflect)]
 pub struct ObjectRaycastSet;
@@ -61,6 +61,8 @@ fn process_select_event(
     mut select_state: ResMut<SelectState>,
     parent_query: Query<&Parent>,
     object_query: Query<Option<&PickSelection>, With<Object>>,
+    layers: Res<LevelLayers>,
+    layer_query: Query<&LevelLayer>,
  //   mut pick_query: Query<&mut PickSelection, Without<Object>>,
     mut selections: EventWriter<PointerSelectEventWaiter>,
     mut deselections: EventWriter<PointerEvent<Deselect>>,
@@ -74,6 +76,17 @@ fn process_select_event(
     {
         info!("select! {:?}", target);
 
+        // Objects of a locked layer can't be selected.
+        if layers.is_locked(entity_layer(*target, &parent_query, &layer_query)) {
+            deselections.send(PointerEvent::new(
+                *pointer_id,
+                pointer_location.to_owned(),
+                *target,
+                Deselect,
+            ));
+            continue;
+        }
+
         if object_query.contains(*target) {
             select_state.entity = Some(target.clone());
             return;
@@ -167,10 +180,17 @@ fn process_picking_events(
     mut events: EventReader<PickingEvent>,
     mut select_state: ResMut<SelectState>,
     mut object_query: Query<&mut PickSelection, With<Object>>,
+    layers: Res<LevelLayers>,
+    parent_query: Query<&Parent>,
+    layer_query: Query<&LevelLayer>,
 ) {
     if let Some(PickingEvent { entity }) = events.iter().last() {
         info!("picking! entity: {:?}", entity);
 
+        if layers.is_locked(entity_layer(*entity, &parent_query, &layer_query)) {
+            return;
+        }
+
         if let Some(mut entity_commands) = commands.get_entity(*entity) {
             for mut pick_selection in object_query.iter_mut() {
                 if pick_selection.is_selected {
@@ -0,0 +1,72 @@
+use std::f32::consts::TAU;
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::DebugLines;
+
+use crate::gui::SelectState;
+use crate::objects::{LightType, Object};
+
+const SEGMENTS: usize = 32;
+const RANGE_COLOR: Color = Color::YELLOW;
+const INNER_COLOR: Color = Color::ORANGE;
+
+// Range of the selected point light, cone of the selected spot light and direction
+// of the selected directional light.
+pub(super) fn process_draw_light_gizmos(
+    select_state: Res<SelectState>,
+    light_query: Query<(&Object, &GlobalTransform)>,
+    mut lines: ResMut<DebugLines>,
+) {
+    let entity = crate::if_none_return!(select_state.entity);
+    let (object, global_transform) = crate::if_err_return!(light_query.get(entity));
+    let light = crate::if_none_return!(object.light.as_ref());
+
+    let position = global_transform.translation();
+    let forward = global_transform.forward();
+    let (right, up) = (global_transform.right(), global_transform.up());
+
+    match light.light_type {
+        LightType::Point => {
+            let range = light.get_range();
+
+            draw_circle(&mut lines, position, Vec3::X * range, Vec3::Y * range, RANGE_COLOR);
+            draw_circle(&mut lines, position, Vec3::X * range, Vec3::Z * range, RANGE_COLOR);
+            draw_circle(&mut lines, position, Vec3::Y * range, Vec3::Z * range, RANGE_COLOR);
+        },
+        LightType::Spot => {
+            let range = light.get_range();
+
+            draw_cone(&mut lines, position, forward, right, up, range, light.get_outer_angle(), RANGE_COLOR);
+            draw_cone(&mut lines, position, forward, right, up, range, light.get_inner_angle(), INNER_COLOR);
+        },
+        LightType::Directional => {
+            let end = position + forward * 2.0;
+
+            lines.line_colored(position, end, 0.0, RANGE_COLOR);
+            lines.line_colored(end, end - forward * 0.3 + right * 0.15, 0.0, RANGE_COLOR);
+            lines.line_colored(end, end - forward * 0.3 - right * 0.15, 0.0, RANGE_COLOR);
+        },
+    };
+}
+
+fn draw_circle(lines: &mut DebugLines, center: Vec3, axis_a: Vec3, axis_b: Vec3, color: Color) {
+    let point = |index: usize| {
+        let angle = index as f32 / SEGMENTS as f32 * TAU;
+        center + axis_a * angle.cos() + axis_b * angle.sin()
+    };
+
+    for index in 0..SEGMENTS {
+        lines.line_colored(point(index), point(index + 1), 0.0, color);
+    }
+}
+
+fn draw_cone(lines: &mut DebugLines, apex: Vec3, forward: Vec3, right: Vec3, up: Vec3, range: f32, angle: f32, color: Color) {
+    let center = apex + forward * range * angle.cos();
+    let radius = range * angle.sin();
+
+    draw_circle(lines, center, right * radius, up * radius, color);
+
+    for index in 0..4 {
+        let side = index as f32 / 4.0 * TAU;
+        lines.line_colored(apex, center + (right * side.cos() + up * side.sin()) * radius, 0.0, color);
+    }
+}
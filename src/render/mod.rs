@@ -5,8 +5,11 @@ use crate::gui::{SelectState, RenderState};
 
 use crate::objects::{CompositeObjectLabel, ObjectType};
 
+use self::lights::process_draw_light_gizmos;
+
 
 //mod navmesh;
+mod lights;
 mod physics;
 
 
@@ -19,6 +22,7 @@ impl Plugin for RenderPlugin {
                 process_drow_hierarchy.run_if(resource_exists::<RenderState>().and_then(
                     |state: Res<RenderState>| state.hierarchy_enabled,
                 )),
+                process_draw_light_gizmos,
             ));
     }
 }  